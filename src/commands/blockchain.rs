@@ -1,6 +1,6 @@
 use crate::{
-    common::JorupConfig,
-    utils::download::{self, Client},
+    common::{JorupConfig, OFFICIAL_REGISTRY},
+    utils::download,
 };
 use structopt::StructOpt;
 use thiserror::Error;
@@ -8,9 +8,31 @@ use thiserror::Error;
 /// Blockchain configuration management
 #[derive(Debug, StructOpt)]
 pub enum Command {
-    /// Download the latest config for blockchains
+    /// Download the latest config for blockchains from all the registries
     Update,
-    /// List blockchains from `jorfile.json`
+    /// List blockchains from the registries
+    List,
+    /// Manage the registries the blockchain configurations are obtained from
+    ///
+    /// The registries added here take precedence over the official registry:
+    /// a blockchain they define hides the official blockchain with the same
+    /// name. Two added registries cannot define the same blockchain.
+    Registry(RegistryCommand),
+}
+
+#[derive(Debug, StructOpt)]
+pub enum RegistryCommand {
+    /// Add a registry. Run `jorup blockchain update` to fetch it.
+    Add {
+        /// The name of the registry
+        name: String,
+        /// HTTP URL of the registry's jorfile, or `file://` path to a local
+        /// jorfile
+        url: String,
+    },
+    /// Remove a registry and its blockchain configurations
+    Remove { name: String },
+    /// List the configured registries
     List,
 }
 
@@ -22,26 +44,41 @@ pub enum Error {
     JorfileLoadFailed(#[source] crate::common::Error),
     #[error("Failed to create the downloader client")]
     DownloaderCreate(#[source] download::Error),
+    #[error("Cannot update the registries")]
+    Registry(#[source] crate::common::Error),
 }
 
 impl Command {
     pub fn run(self, mut cfg: JorupConfig) -> Result<(), Error> {
         match self {
             Command::Update => {
-                let mut client = Client::new().map_err(Error::DownloaderCreate)?;
+                let mut client = cfg.client().map_err(Error::DownloaderCreate)?;
                 cfg.sync_jorfile(&mut client).map_err(Error::SyncFailed)?;
             }
             Command::List => {
                 let config = cfg.load_jor().map_err(Error::JorfileLoadFailed)?;
                 for blockchain in config.blockchains().iter() {
                     println!(
-                        "\t{}\nGenesis block hash: {}\n{}\n",
+                        "\t{}\nGenesis block hash: {}\nRegistry: {}\n{}\n",
                         blockchain.name(),
                         blockchain.block0_hash(),
+                        blockchain.registry(),
                         blockchain.description()
                     );
                 }
             }
+            Command::Registry(RegistryCommand::Add { name, url }) => {
+                cfg.add_registry(name, url).map_err(Error::Registry)?;
+            }
+            Command::Registry(RegistryCommand::Remove { name }) => {
+                cfg.remove_registry(&name).map_err(Error::Registry)?;
+            }
+            Command::Registry(RegistryCommand::List) => {
+                for registry in cfg.registries().map_err(Error::Registry)? {
+                    println!("{}\t{}", registry.name(), registry.url());
+                }
+                println!("{}\t(official)", OFFICIAL_REGISTRY);
+            }
         }
         Ok(())
     }
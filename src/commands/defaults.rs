@@ -7,7 +7,9 @@ use thiserror::Error;
 #[derive(Debug, StructOpt)]
 pub struct Command {
     /// The blockchain to get the configuration for
-    blockchain: String,
+    ///
+    /// Defaults to the `default-blockchain` setting.
+    blockchain: Option<String>,
 
     #[structopt(long, default_value = "yaml")]
     format: ConfigFormat,
@@ -35,10 +37,10 @@ struct ConfigFormatError;
 
 impl Command {
     pub fn run(&self, mut cfg: JorupConfig) -> Result<(), Error> {
-        use std::net::ToSocketAddrs;
+        use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
-        let blockchain =
-            Blockchain::load(&mut cfg, &self.blockchain).map_err(Error::NoValidBlockchain)?;
+        let blockchain = Blockchain::load_or_default(&mut cfg, self.blockchain.as_deref())
+            .map_err(Error::NoValidBlockchain)?;
         blockchain.prepare().map_err(Error::NoValidBlockchain)?;
 
         let output = config::Config {
@@ -48,11 +50,11 @@ impl Command {
                 format: "plain".to_string(),
             }],
             p2p: Some(config::P2p {
-                public_address: Some("/ip4/127.0.0.1/tcp/3000".to_string()),
+                public_address: Some(format!("/ip4/127.0.0.1/tcp/{}", cfg.p2p_port())),
                 trusted_peers: blockchain.entry().trusted_peers().to_vec(),
             }),
             rest: Some(config::Rest {
-                listen: "127.0.0.1:8080".to_socket_addrs().unwrap().next().unwrap(),
+                listen: SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), cfg.rest_port()),
             }),
             storage: Some(blockchain.get_node_storage()),
             secret_files: vec![blockchain.get_node_secret()],
@@ -1,7 +1,12 @@
 use crate::{
     common::JorupConfig,
-    utils::{blockchain::Blockchain, runner::RunnerControl},
+    utils::{
+        blockchain::Blockchain,
+        rest::{NodeSettings, NodeStats, RestClient},
+        runner::{RunnerControl, RunnerInfo},
+    },
 };
+use serde::Serialize;
 use structopt::StructOpt;
 use thiserror::Error;
 
@@ -9,7 +14,14 @@ use thiserror::Error;
 #[derive(Debug, StructOpt)]
 pub struct Command {
     /// The blockchain to run jormungandr for
-    blockchain: String,
+    ///
+    /// Defaults to the `default-blockchain` setting.
+    blockchain: Option<String>,
+
+    /// Print the runner file, the node statistics and the blockchain
+    /// settings as JSON
+    #[structopt(long)]
+    json: bool,
 }
 
 #[derive(Debug, Error)]
@@ -18,20 +30,147 @@ pub enum Error {
     NoValidBlockchain(#[source] crate::utils::blockchain::Error),
     #[error("Unable to start the runner controller")]
     CannotStartRunnerController(#[source] crate::utils::runner::Error),
-    #[error("Cannot collect node's info")]
-    CannotCollectInfo(#[source] crate::utils::runner::Error),
+    #[error("Cannot check if the node is running")]
+    CannotCheckNode(#[source] crate::utils::runner::Error),
+    #[error("The REST API of the node is not enabled, its statistics are not available")]
+    RestDisabled,
+    #[error("Cannot get the node statistics")]
+    CannotGetStats(#[source] crate::utils::rest::Error),
+    #[error("Cannot get the blockchain settings")]
+    CannotGetSettings(#[source] crate::utils::rest::Error),
+}
+
+#[derive(Serialize)]
+struct Info<'a> {
+    runner: &'a RunnerInfo,
+    /// missing while the supervisor waits to restart the node, or when the
+    /// REST API of the node is not available
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stats: Option<&'a NodeStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    settings: Option<&'a NodeSettings>,
 }
 
 impl Command {
     pub fn run(self, mut cfg: JorupConfig) -> Result<(), Error> {
-        let blockchain =
-            Blockchain::load(&mut cfg, &self.blockchain).map_err(Error::NoValidBlockchain)?;
+        let blockchain = Blockchain::load_or_default(&mut cfg, self.blockchain.as_deref())
+            .map_err(Error::NoValidBlockchain)?;
         blockchain.prepare().map_err(Error::NoValidBlockchain)?;
 
-        let mut runner =
+        let control =
             RunnerControl::load(&blockchain).map_err(Error::CannotStartRunnerController)?;
 
-        runner.settings().map_err(Error::CannotCollectInfo)?;
-        runner.info().map_err(Error::CannotCollectInfo)
+        // a loaded runner always has its runner file
+        let runner = control.info().unwrap();
+        let node_running = runner.is_node_running().map_err(Error::CannotCheckNode)?;
+
+        // the runner file is shown even if the statistics cannot be
+        // obtained, the error is returned once it is shown
+        let details = if node_running {
+            rest_details(runner).map(Some)
+        } else {
+            Ok(None)
+        };
+        let available = details.as_ref().ok().and_then(Option::as_ref);
+
+        if self.json {
+            let info = Info {
+                runner,
+                stats: available.map(|(_, stats, _)| stats),
+                settings: available.map(|(_, _, settings)| settings),
+            };
+            println!("{}", serde_json::to_string_pretty(&info).unwrap());
+            return details.map(|_| ());
+        }
+
+        println!("Blockchain:  {}", blockchain.entry().name());
+        println!("PID:         {}", runner.pid());
+        if let Some(supervisor) = runner.supervisor() {
+            println!(
+                "Supervisor:  PID {}, restart {}, restarted {} times (at most {} in a row)",
+                supervisor.pid(),
+                supervisor.restart(),
+                supervisor.restarts(),
+                supervisor.max_restarts()
+            );
+            if let Some(status) = supervisor.last_exit_status() {
+                println!("Last exit:   {}", status);
+            }
+        }
+
+        let (rest, stats, settings) = match details? {
+            Some(details) => details,
+            None => {
+                println!("State:       Exited, waiting to be restarted");
+                return Ok(());
+            }
+        };
+
+        println!("Version:     {}", stats.version);
+        println!("State:       {}", stats.state);
+        if let Some(uptime) = stats.uptime {
+            println!("Uptime:      {}", format_uptime(uptime));
+        }
+        println!("REST API:    {}", rest.base_url());
+
+        if let (Some(height), Some(date), Some(hash)) = (
+            &stats.last_block_height,
+            &stats.last_block_date,
+            &stats.last_block_hash,
+        ) {
+            println!("Tip:         height {} at {} ({})", height, date, hash);
+        }
+        if let Some(time) = &stats.last_block_time {
+            println!("Tip time:    {}", time);
+        }
+        if let (Some(blocks), Some(txs)) = (stats.block_recv_cnt, stats.tx_recv_cnt) {
+            println!("Received:    {} blocks, {} transactions", blocks, txs);
+        }
+        if let Some(connected) = stats.peer_connected_cnt {
+            match stats.peer_total_cnt {
+                Some(total) => println!("Peers:       {} connected, {} known", connected, total),
+                None => println!("Peers:       {} connected", connected),
+            }
+        }
+
+        println!(
+            "Block 0:     {} ({})",
+            settings.block0_hash, settings.block0_time
+        );
+        println!(
+            "Consensus:   {}, {}s slots, {} slots per epoch",
+            settings.consensus_version, settings.slot_duration, settings.slots_per_epoch
+        );
+        println!(
+            "Fees:        constant {}, coefficient {}, certificate {}",
+            settings.fees.constant, settings.fees.coefficient, settings.fees.certificate
+        );
+
+        Ok(())
+    }
+}
+
+/// The statistics and the settings of a running node, from its REST API
+fn rest_details(runner: &RunnerInfo) -> Result<(RestClient, NodeStats, NodeSettings), Error> {
+    let rest_port = runner.rest_port().ok_or(Error::RestDisabled)?;
+    let rest = RestClient::new(rest_port).map_err(Error::CannotGetStats)?;
+    let stats = rest.stats().map_err(Error::CannotGetStats)?;
+    let settings = rest.settings().map_err(Error::CannotGetSettings)?;
+    Ok((rest, stats, settings))
+}
+
+/// Format a number of seconds as days, hours, minutes and seconds
+pub fn format_uptime(seconds: u64) -> String {
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    let (hours, seconds) = (seconds / 3600, seconds % 3600);
+    let (minutes, seconds) = (seconds / 60, seconds % 60);
+    if days > 0 {
+        format!("{}d {:02}h {:02}m {:02}s", days, hours, minutes, seconds)
+    } else if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
     }
 }
@@ -0,0 +1,356 @@
+use crate::{common::JorupConfig, utils::blockchain::Blockchain};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, TimeZone};
+use std::{
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use structopt::StructOpt;
+use thiserror::Error;
+
+/// How often the log file is checked for new lines with `--follow`
+const FOLLOW_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Show the logs of a node running in the background
+///
+/// The logs of the previous nodes are kept next to `NODE.logs` in the
+/// directory of the blockchain, see the `keep-logs` setting.
+#[derive(Debug, StructOpt)]
+pub struct Command {
+    /// The blockchain of the node
+    ///
+    /// Defaults to the `default-blockchain` setting.
+    blockchain: Option<String>,
+
+    /// Keep waiting for new lines
+    #[structopt(short, long)]
+    follow: bool,
+
+    /// Only show the last lines
+    #[structopt(short = "n", long)]
+    lines: Option<usize>,
+
+    /// Only show the lines of this level or above: trace, debug, info, warn,
+    /// error or crit
+    #[structopt(long)]
+    level: Option<Level>,
+
+    /// Only show the lines written since a duration ago, for example `10m`,
+    /// `2h` or `1d`, or since a date, for example `2020-04-20T10:00:00Z`
+    #[structopt(long, parse(try_from_str = parse_since))]
+    since: Option<DateTime<Local>>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Cannot find the blockchain")]
+    NoValidBlockchain(#[source] crate::utils::blockchain::Error),
+    #[error("No logs yet, the node has not been started with `jorup run --daemon`: {0}")]
+    NoLogs(PathBuf),
+    #[error("Cannot read the logs: {1}")]
+    CannotReadLogs(#[source] io::Error, PathBuf),
+    #[error("Cannot write the logs")]
+    CannotWrite(#[source] io::Error),
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid time '{0}', expected a duration such as 10m, 2h or 1d, or a date")]
+pub struct InvalidTime(String);
+
+#[derive(Debug, Error)]
+#[error("Invalid level '{0}', expected one of trace, debug, info, warn, error or crit")]
+pub struct InvalidLevel(String);
+
+/// The levels of the jormungandr logs, from the least to the most severe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Critical,
+}
+
+/// Decides which lines to show. Lines without level nor time, such as the
+/// continuation of a multi-line message, follow the decision of the previous
+/// line.
+struct Filter {
+    level: Option<Level>,
+    since: Option<DateTime<Local>>,
+    keep: bool,
+}
+
+impl Command {
+    pub fn run(self, mut cfg: JorupConfig) -> Result<(), Error> {
+        let blockchain = Blockchain::load_or_default(&mut cfg, self.blockchain.as_deref())
+            .map_err(Error::NoValidBlockchain)?;
+        let path = blockchain.get_log_file();
+
+        let mut filter = Filter {
+            level: self.level,
+            since: self.since,
+            keep: self.level.is_none() && self.since.is_none(),
+        };
+
+        let mut position = 0;
+        let mut created = path.metadata().and_then(|metadata| metadata.created()).ok();
+        if path.is_file() {
+            let content = read_from(&path, 0)?;
+            position = content.len() as u64;
+            let content = String::from_utf8_lossy(&content);
+            let lines: Vec<_> = content.lines().filter(|line| filter.keep(line)).collect();
+            let skip = match self.lines {
+                Some(count) => lines.len().saturating_sub(count),
+                None => 0,
+            };
+            if !print_lines(&lines[skip..])? {
+                return Ok(());
+            }
+        } else if !self.follow {
+            return Err(Error::NoLogs(path));
+        }
+
+        if !self.follow {
+            return Ok(());
+        }
+
+        let mut partial = Vec::new();
+        loop {
+            std::thread::sleep(FOLLOW_INTERVAL);
+
+            let metadata = match path.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            let len = metadata.len();
+            // the log was rotated when the node was started again
+            let rotated = match (created, metadata.created()) {
+                (Some(created), Ok(now_created)) => created != now_created,
+                _ => len < position,
+            };
+            if rotated {
+                created = metadata.created().ok();
+                position = 0;
+                partial.clear();
+            }
+            if len == position {
+                continue;
+            }
+
+            let content = read_from(&path, position)?;
+            position += content.len() as u64;
+            partial.extend_from_slice(&content);
+
+            // only show complete lines, the rest is shown with the next read
+            let end = match partial.iter().rposition(|byte| *byte == b'\n') {
+                Some(end) => end + 1,
+                None => continue,
+            };
+            let complete: Vec<u8> = partial.drain(..end).collect();
+            let complete = String::from_utf8_lossy(&complete);
+            let lines: Vec<_> = complete.lines().filter(|line| filter.keep(line)).collect();
+            if !print_lines(&lines)? {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn read_from(path: &Path, position: u64) -> Result<Vec<u8>, Error> {
+    let mut content = Vec::new();
+    File::open(path)
+        .and_then(|mut file| {
+            file.seek(SeekFrom::Start(position))?;
+            file.read_to_end(&mut content)
+        })
+        .map_err(|e| Error::CannotReadLogs(e, path.to_path_buf()))?;
+    Ok(content)
+}
+
+/// Print the lines, returns false if the output was closed, for example by
+/// piping into `head`
+fn print_lines(lines: &[&str]) -> Result<bool, Error> {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    for line in lines {
+        match writeln!(stdout, "{}", line) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(false),
+            Err(err) => return Err(Error::CannotWrite(err)),
+        }
+    }
+    match stdout.flush() {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(false),
+        Err(err) => Err(Error::CannotWrite(err)),
+    }
+}
+
+impl Filter {
+    fn keep(&mut self, line: &str) -> bool {
+        let (level, time) = parse_line(line);
+        if level.is_some() || time.is_some() {
+            let level_matches = match (self.level, level) {
+                (Some(min), Some(level)) => level >= min,
+                _ => true,
+            };
+            let time_matches = match (self.since, time) {
+                (Some(since), Some(time)) => time >= since,
+                _ => true,
+            };
+            self.keep = level_matches && time_matches;
+        }
+        self.keep
+    }
+}
+
+/// The level and time of a line of the logs, in the `plain` format of
+/// jormungandr (`Apr 20 10:00:00.000 INFO message`) or in the `json` format
+fn parse_line(line: &str) -> (Option<Level>, Option<DateTime<Local>>) {
+    if line.starts_with('{') {
+        let value: serde_json::Value = match serde_json::from_str(line) {
+            Ok(value) => value,
+            Err(_) => return (None, None),
+        };
+        let level = value["level"].as_str().and_then(|level| level.parse().ok());
+        let time = value["ts"]
+            .as_str()
+            .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+            .map(|ts| ts.with_timezone(&Local));
+        return (level, time);
+    }
+
+    let tokens: Vec<_> = line.split_whitespace().take(4).collect();
+    if tokens.len() < 4 {
+        return (None, None);
+    }
+    // the plain format does not have the year
+    let now = Local::now();
+    let time = NaiveDateTime::parse_from_str(
+        &format!("{} {}", now.year(), tokens[..3].join(" ")),
+        "%Y %b %d %H:%M:%S%.f",
+    )
+    .ok()
+    .and_then(|time| Local.from_local_datetime(&time).earliest())
+    // a line of december read in january
+    .map(|time| {
+        if time > now + Duration::days(1) {
+            time.with_year(now.year() - 1).unwrap_or(time)
+        } else {
+            time
+        }
+    });
+    let level = time.and_then(|_| tokens[3].parse().ok());
+    (level, time)
+}
+
+fn parse_since(since: &str) -> Result<DateTime<Local>, InvalidTime> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(since) {
+        return Ok(time.with_timezone(&Local));
+    }
+    for format in &["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Some(time) = NaiveDateTime::parse_from_str(since, format)
+            .ok()
+            .and_then(|time| Local.from_local_datetime(&time).earliest())
+        {
+            return Ok(time);
+        }
+    }
+
+    let invalid = || InvalidTime(since.to_string());
+    let split = since
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (count, unit) = since.split_at(split);
+    let count: i64 = count.parse().map_err(|_| invalid())?;
+    let duration = match unit {
+        "s" => Duration::seconds(count),
+        "m" => Duration::minutes(count),
+        "h" => Duration::hours(count),
+        "d" => Duration::days(count),
+        _ => return Err(invalid()),
+    };
+    Ok(Local::now() - duration)
+}
+
+impl FromStr for Level {
+    type Err = InvalidLevel;
+
+    /// Parse the level given on the command line or found in the logs
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "trace" | "trce" => Ok(Level::Trace),
+            "debug" | "debg" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" | "warning" => Ok(Level::Warn),
+            "error" | "erro" => Ok(Level::Error),
+            "crit" | "critical" => Ok(Level::Critical),
+            _ => Err(InvalidLevel(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+
+    #[test]
+    fn plain_line() {
+        let (level, time) = parse_line("Jan 02 10:20:30.123 WARN peer disconnected, task: network");
+        assert_eq!(level, Some(Level::Warn));
+        let time = time.unwrap();
+        assert_eq!((time.month(), time.day()), (1, 2));
+        assert_eq!((time.hour(), time.minute(), time.second()), (10, 20, 30));
+    }
+
+    #[test]
+    fn json_line() {
+        let (level, time) =
+            parse_line(r#"{"msg":"started","level":"INFO","ts":"2026-10-16T10:20:30+00:00"}"#);
+        assert_eq!(level, Some(Level::Info));
+        assert_eq!(
+            time,
+            Some(
+                DateTime::parse_from_rfc3339("2026-10-16T10:20:30+00:00")
+                    .unwrap()
+                    .with_timezone(&Local)
+            )
+        );
+    }
+
+    #[test]
+    fn continuation_line() {
+        assert_eq!(parse_line("  caused by: connection refused"), (None, None));
+        assert_eq!(parse_line(""), (None, None));
+        assert_eq!(parse_line("{not json"), (None, None));
+    }
+
+    #[test]
+    fn since_duration() {
+        let since = parse_since("10m").unwrap();
+        let expected = Local::now() - Duration::minutes(10);
+        assert!((expected - since).num_seconds().abs() < 5);
+        assert!(parse_since("2d").unwrap() < parse_since("1h").unwrap());
+    }
+
+    #[test]
+    fn since_date() {
+        let expected = Local.ymd(2026, 10, 16).and_hms(10, 20, 30);
+        assert_eq!(parse_since("2026-10-16 10:20:30").unwrap(), expected);
+        assert_eq!(parse_since("2026-10-16T10:20:30").unwrap(), expected);
+        assert_eq!(
+            parse_since("2026-10-16T10:20:30+00:00").unwrap(),
+            DateTime::parse_from_rfc3339("2026-10-16T10:20:30+00:00").unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_since() {
+        for since in &["", "10", "m", "10y", "yesterday", "2026-10-16"] {
+            assert!(parse_since(since).is_err(), "{}", since);
+        }
+    }
+}
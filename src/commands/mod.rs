@@ -1,14 +1,20 @@
 mod blockchain;
 mod defaults;
 mod info;
+mod logs;
 mod node;
+mod overrides;
 mod run;
+mod settings;
 mod setup;
 mod shutdown;
+mod status;
+mod supervise;
 mod wallet;
 
 pub use setup::Install;
 
+use crate::{settings::Settings, utils::github::ReleaseSource};
 use std::path::PathBuf;
 use structopt::StructOpt;
 use thiserror::Error;
@@ -25,7 +31,8 @@ pub struct RootCmd {
     ///
     /// Set the directory path where jorup will install the different releases
     /// or different blockchains. Mainly remember to set `$JORUP_HOME/bin` value to
-    /// your $PATH for easy access to the default release's tools.
+    /// your $PATH for easy access to the default release's tools. Defaults to
+    /// `$JORUP_HOME` or `~/.jorup`.
     #[structopt(long)]
     jorup_home: Option<PathBuf>,
 
@@ -47,6 +54,25 @@ pub struct RootCmd {
     #[structopt(long)]
     offline: bool,
 
+    /// Where to obtain the jormungandr releases from
+    ///
+    /// Either `github:<owner>/<repo>` for a GitHub repository,
+    /// `github:<api-url>/<owner>/<repo>` for a GitHub compatible API (GitHub
+    /// Enterprise), or a local directory or an HTTP URL of a mirror with a
+    /// `<version>/<asset>` layout. HTTP mirrors must also serve an
+    /// `index.json` listing their releases. Defaults to
+    /// `github:input-output-hk/jormungandr`.
+    #[structopt(long)]
+    release_source: Option<ReleaseSource>,
+
+    /// Wait for the GitHub API rate limit to reset instead of failing
+    ///
+    /// Anonymous requests to the GitHub API are limited. Set the `GITHUB_TOKEN`
+    /// environment variable or the `github-token` setting to use a higher,
+    /// authenticated, limit.
+    #[structopt(long)]
+    wait_for_rate_limit: bool,
+
     #[structopt(subcommand)]
     command: Command,
 }
@@ -65,11 +91,18 @@ enum Command {
     Run(run::Command),
     Shutdown(shutdown::Command),
     Info(info::Command),
+    Status(status::Command),
+    Logs(logs::Command),
     Wallet(wallet::Command),
     Setup(setup::Command),
     Blockchain(blockchain::Command),
     Node(node::Command),
     Defaults(defaults::Command),
+    Settings(settings::Command),
+    Override(overrides::Command),
+
+    #[structopt(setting = structopt::clap::AppSettings::Hidden)]
+    Supervise(supervise::Command),
 }
 
 #[derive(Debug, Error)]
@@ -85,6 +118,10 @@ pub enum Error {
     #[error(transparent)]
     Info(#[from] info::Error),
     #[error(transparent)]
+    Status(#[from] status::Error),
+    #[error(transparent)]
+    Logs(#[from] logs::Error),
+    #[error(transparent)]
     Wallet(#[from] wallet::Error),
     #[error(transparent)]
     Setup(#[from] setup::Error),
@@ -92,15 +129,39 @@ pub enum Error {
     Node(#[from] node::Error),
     #[error(transparent)]
     Defaults(#[from] defaults::Error),
+    #[error(transparent)]
+    Settings(#[from] settings::Error),
+    #[error(transparent)]
+    Override(#[from] overrides::Error),
+    #[error(transparent)]
+    Supervise(#[from] supervise::Error),
 }
 
 impl Cmd for RootCmd {
     type Err = Error;
 
     fn run(self) -> Result<(), Self::Err> {
-        let cfg = crate::common::JorupConfig::new(self.jorup_home, self.jorfile, self.offline)?;
+        // the supervisor gets everything it needs on its command line, it
+        // must not depend on the settings nor the jorfile
+        let command = match self.command {
+            Command::Supervise(cmd) => return Ok(cmd.run()?),
+            command => command,
+        };
+
+        // only the flags given on the command line override the settings
+        let settings = Settings {
+            offline: if self.offline { Some(true) } else { None },
+            release_source: self.release_source,
+            wait_for_rate_limit: if self.wait_for_rate_limit {
+                Some(true)
+            } else {
+                None
+            },
+            ..Settings::default()
+        };
+        let cfg = crate::common::JorupConfig::new(self.jorup_home, self.jorfile, settings)?;
 
-        match self.command {
+        match command {
             Command::Completions { shell } => Self::clap().gen_completions_to(
                 env!("CARGO_PKG_NAME"),
                 shell,
@@ -109,11 +170,16 @@ impl Cmd for RootCmd {
             Command::Run(cmd) => cmd.run(cfg)?,
             Command::Shutdown(cmd) => cmd.run(cfg)?,
             Command::Info(cmd) => cmd.run(cfg)?,
+            Command::Status(cmd) => cmd.run(cfg)?,
+            Command::Logs(cmd) => cmd.run(cfg)?,
             Command::Wallet(cmd) => cmd.run(cfg)?,
             Command::Setup(cmd) => cmd.run(cfg)?,
             Command::Blockchain(cmd) => cmd.run(cfg)?,
             Command::Node(cmd) => cmd.run(cfg)?,
             Command::Defaults(cmd) => cmd.run(cfg)?,
+            Command::Settings(cmd) => cmd.run(cfg)?,
+            Command::Override(cmd) => cmd.run(cfg)?,
+            Command::Supervise(_) => unreachable!(),
         }
 
         Ok(())
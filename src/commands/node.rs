@@ -2,12 +2,22 @@ use crate::{
     common::JorupConfig,
     utils::{
         blockchain::Blockchain,
+        cache::MetadataCache,
         download::{self, Client},
-        github,
-        release::{list_installed_releases, Error as ReleaseError, Release},
-        version::{Version, VersionReq},
+        overrides::{self, Override},
+        release::{
+            list_installed_releases, unset_default, AssetRemote, Error as ReleaseError, Release,
+            PROXIES,
+        },
+        version::{Channel, Version, VersionReq},
     },
 };
+use indicatif::HumanBytes;
+use std::{
+    io::{self, BufRead, BufReader},
+    path::PathBuf,
+    process::{Command as Process, ExitStatus, Stdio},
+};
 use structopt::StructOpt;
 use thiserror::Error;
 
@@ -17,28 +27,113 @@ pub enum Command {
     /// Install the specified version of Jorumngandr. If no version or
     /// blockchain was specified it will download the latest stable version.
     Install {
-        /// Install a particular version of Jormungandr. Cannot be used
-        /// alongside --blockchain
-        #[structopt(short, long)]
-        version: Option<Version>,
+        /// Install a particular version of Jormungandr, or the newest release
+        /// of a channel: `stable`, `rc`, `beta`, `latest` or `nightly`. Cannot
+        /// be used alongside --blockchain
+        #[structopt(short, long, parse(try_from_str = VersionReq::parse_exact))]
+        version: Option<VersionReq>,
 
         /// Install the latest version compatible with the specified blockchain
         #[structopt(short, long)]
         blockchain: Option<String>,
 
+        /// Install the latest version compatible with each blockchain of the
+        /// jorfile. Cannot be used alongside --version or --blockchain
+        #[structopt(long, conflicts_with_all = &["version", "blockchain", "make-default"])]
+        all_blockchains: bool,
+
         /// Make the installed version default
         #[structopt(long)]
         make_default: bool,
+
+        /// Install the release even if no checksum is published for it
+        #[structopt(long)]
+        allow_unverified: bool,
     },
     /// List locally installed Jormungandr releases
+    ///
+    /// The default release is marked with a `*`. The blockchains listed for a
+    /// release are the blockchains of the jorfile it is compatible with, after
+    /// the linked directory of a release registered with `jorup node link`.
     List,
+    /// Set the default release, run by the `jormungandr` and `jcli` proxies
+    Default {
+        /// An installed version, a version requirement or a release channel.
+        /// The latest installed release matching it becomes the default.
+        #[structopt(parse(try_from_str = VersionReq::parse_exact))]
+        version: VersionReq,
+    },
     /// Remove the specified release
-    Remove { version: Version },
+    Remove {
+        #[structopt(parse(try_from_str = Version::parse_name))]
+        version: Version,
+    },
+    /// Build Jormungandr from a local source checkout and register it as a
+    /// custom release
+    ///
+    /// Runs `cargo build --release` in the checkout and copies `jormungandr`
+    /// and `jcli` into the release. The release can then be used like any
+    /// other with its name, for example `jorup run -v <name>`. Building again
+    /// with the same name replaces the binaries.
+    Build {
+        /// The source checkout of Jormungandr
+        path: PathBuf,
+
+        /// The name of the release, defaults to the name of the checkout
+        /// directory. It must start with a letter and must not be a version
+        /// or a release channel.
+        #[structopt(long)]
+        name: Option<String>,
+
+        /// Make the built release default
+        #[structopt(long)]
+        make_default: bool,
+    },
+    /// Register a directory containing `jormungandr` and `jcli` as a custom
+    /// release
+    ///
+    /// The directory is linked, not copied: rebuilding the binaries in place
+    /// updates the release. The release can then be used like any other with
+    /// its name, for example `jorup run -v <name>`.
+    Link {
+        /// The name of the release. It must start with a letter and must not
+        /// be a version or a release channel.
+        name: String,
+
+        /// The directory containing the binaries, for example the
+        /// `target/debug` directory of a source checkout
+        dir: PathBuf,
+    },
+    /// Remove a release registered with `jorup node link`
+    ///
+    /// The linked directory is left untouched.
+    Unlink {
+        #[structopt(parse(try_from_str = Version::parse_name))]
+        name: Version,
+    },
+    /// Remove the releases that are not used anymore
+    ///
+    /// A release is kept if it is the default release, if it is pinned by an
+    /// override (see `jorup override`), if it is the latest installed
+    /// release compatible with a blockchain of the jorfile or if it is a
+    /// custom release, built with `jorup node build` or linked with
+    /// `jorup node link`.
+    Prune {
+        /// Keep the downloaded archives of the remaining releases. They are
+        /// not needed to run the releases, use `--keep-archives=false` to
+        /// remove them.
+        #[structopt(long, default_value = "true", parse(try_from_str))]
+        keep_archives: bool,
+
+        /// Only list what would be removed and the space it would reclaim
+        #[structopt(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Debug, Error)]
 pub enum Error {
-    #[error("Cannot run this command offline")]
+    #[error("The release is not downloaded yet, cannot install it offline")]
     Offline,
     #[error("Cannot load the requested blockchain")]
     NoValidBlockchain(#[from] crate::utils::blockchain::Error),
@@ -54,101 +149,514 @@ pub enum Error {
     ReleasesList(#[source] ReleaseError),
     #[error("Failed to remove a release")]
     RemoveRelease(#[source] std::io::Error),
+    #[error("Cannot load the blockchains")]
+    Jorfile(#[source] crate::common::Error),
+    #[error("Failed to install the releases of {0} blockchain(s)")]
+    InstallFailed(usize),
+    #[error("Cannot find the releases pinned by the overrides")]
+    Overrides(#[source] overrides::Error),
     #[error("Failed to create the downloader client")]
     DownloaderCreate(#[source] download::Error),
+    #[error("Cannot find the source checkout {1}")]
+    Checkout(#[source] io::Error, PathBuf),
+    #[error("Invalid release name '{0}', it must start with a letter and must not be a version or a release channel")]
+    InvalidName(String),
+    #[error("Cannot run cargo")]
+    BuildCommand(#[source] io::Error),
+    #[error("Build failed ({0})")]
+    BuildFailed(ExitStatus),
+    #[error("The build did not produce the {0} binary")]
+    MissingBinary(&'static str),
+    #[error("Cannot copy {1} into the release")]
+    CannotCopyBinary(#[source] io::Error, PathBuf),
+    #[error("{0} is a linked release, remove it with `jorup node unlink` first")]
+    Linked(Version),
+    #[error("Cannot find the directory {1}")]
+    LinkDir(#[source] io::Error, PathBuf),
+    #[error("Failed to link or unlink the release")]
+    Link(#[source] ReleaseError),
+    #[error("Cannot unset the default release")]
+    UnsetDefault(#[source] ReleaseError),
 }
 
 impl Command {
     pub fn run(self, cfg: JorupConfig) -> Result<(), Error> {
         match self {
+            Command::Install {
+                all_blockchains: true,
+                allow_unverified,
+                ..
+            } => install_all(cfg, allow_unverified),
             Command::Install {
                 version,
                 blockchain,
                 make_default,
-            } => install(cfg, version, blockchain, make_default),
+                allow_unverified,
+                ..
+            } => install(cfg, version, blockchain, make_default, allow_unverified),
             Command::List => list(cfg),
+            Command::Default { version } => default(cfg, version),
             Command::Remove { version } => remove(cfg, version),
+            Command::Build {
+                path,
+                name,
+                make_default,
+            } => build(cfg, path, name, make_default),
+            Command::Link { name, dir } => link(cfg, name, dir),
+            Command::Unlink { name } => unlink(cfg, name),
+            Command::Prune {
+                keep_archives,
+                dry_run,
+            } => prune(cfg, keep_archives, dry_run),
         }
     }
 }
 
 fn install(
     mut cfg: JorupConfig,
-    version: Option<Version>,
+    version: Option<VersionReq>,
     blockchain: Option<String>,
     make_default: bool,
+    allow_unverified: bool,
 ) -> Result<(), Error> {
-    if cfg.offline() {
-        return Err(Error::Offline);
-    }
-
     if version.is_some() && blockchain.is_some() {
         return Err(Error::MustNotSpecifyBlockchainAndVersion);
     }
 
-    let load_latest = version.is_none() && blockchain.is_none();
-
     let version_req = match version {
         None => match blockchain {
-            None => VersionReq::Latest,
+            None => VersionReq::Channel(Channel::Stable),
             Some(blockchain_name) => Blockchain::load(&mut cfg, &blockchain_name)?
                 .jormungandr_version_req()
                 .clone(),
         },
-        Some(version) => VersionReq::exact(version),
+        Some(version_req) => version_req,
     };
 
-    let mut client = Client::new().map_err(Error::DownloaderCreate)?;
+    let mut client = cfg.client().map_err(Error::DownloaderCreate)?;
+    let cache = MetadataCache::new(&cfg);
+
+    let release = resolve(&mut cfg, &mut client, &cache, version_req)?;
 
-    let release = if load_latest {
-        let gh_release = github::find_matching_release(&mut client, version_req)?;
-        Release::new(&mut cfg, gh_release.version().clone()).map_err(Error::ReleaseLoad)?
+    // the archive of an installed release may have been pruned
+    if release.asset_need_open() {
+        fetch(&cfg, &mut client, &cache, &release, allow_unverified)?;
     } else {
-        match Release::load(&mut cfg, &version_req) {
-            Ok(release) => release,
-            Err(ReleaseError::NoCompatibleReleaseInstalled(_)) => {
-                let gh_release = github::find_matching_release(&mut client, version_req)?;
-                Release::new(&mut cfg, gh_release.version().clone()).map_err(Error::ReleaseLoad)?
+        println!("**** {} is already installed", release.version());
+    }
+
+    if make_default {
+        release.make_default(&cfg).map_err(Error::ReleaseLoad)?;
+    }
+
+    Ok(())
+}
+
+/// Install the latest release compatible with each blockchain, each release
+/// is installed once
+fn install_all(mut cfg: JorupConfig, allow_unverified: bool) -> Result<(), Error> {
+    let blockchains: Vec<_> = cfg
+        .load_jor()
+        .map_err(Error::Jorfile)?
+        .blockchains()
+        .iter()
+        .map(|blockchain| {
+            (
+                blockchain.name().to_string(),
+                blockchain.jormungandr_versions().clone(),
+            )
+        })
+        .collect();
+
+    let mut client = cfg.client().map_err(Error::DownloaderCreate)?;
+    let cache = MetadataCache::new(&cfg);
+
+    let mut installed = Vec::new();
+    let mut summary = Vec::new();
+    let mut failures = 0;
+    for (name, version_req) in blockchains {
+        let result =
+            resolve(&mut cfg, &mut client, &cache, version_req.clone()).and_then(|release| {
+                let version = release.version().clone();
+                if !installed.contains(&version) && release.asset_need_open() {
+                    println!("**** installing {} for {}", version, name);
+                    fetch(&cfg, &mut client, &cache, &release, allow_unverified)?;
+                }
+                installed.push(version.clone());
+                Ok(version)
+            });
+
+        let status = match result {
+            Ok(version) => version.to_string(),
+            Err(err) => {
+                failures += 1;
+                let mut message = err.to_string();
+                let mut source = std::error::Error::source(&err);
+                while let Some(err) = source {
+                    message = format!("{}: {}", message, err);
+                    source = err.source();
+                }
+                eprintln!("ERROR: cannot install a release for {}: {}", name, message);
+                "failed".to_string()
             }
-            Err(err) => return Err(Error::ReleaseLoad(err)),
+        };
+        summary.push((name, version_req, status));
+    }
+
+    println!();
+    println!("{:<20} {:<16} RELEASE", "BLOCKCHAIN", "REQUIREMENT");
+    for (name, version_req, status) in summary {
+        println!("{:<20} {:<16} {}", name, version_req.to_string(), status);
+    }
+
+    if failures > 0 {
+        return Err(Error::InstallFailed(failures));
+    }
+    Ok(())
+}
+
+/// Find the release to install for the given requirement
+fn resolve(
+    cfg: &mut JorupConfig,
+    client: &mut Client,
+    cache: &MetadataCache,
+    version_req: VersionReq,
+) -> Result<Release, Error> {
+    // a channel moves on with new releases: look for its newest release,
+    // unless offline where the installed releases are the best we know of
+    let load_remote = version_req.channel().is_some() && !cfg.offline();
+
+    if !load_remote {
+        if let Some(release) =
+            Release::load_installed(cfg, &version_req).map_err(Error::ReleaseLoad)?
+        {
+            return Ok(release);
         }
-    };
+    }
+
+    let gh_release = cfg
+        .release_source()
+        .find_matching_release(client, cache, version_req)?;
+    Release::new(cfg, gh_release.version().clone()).map_err(Error::ReleaseLoad)
+}
+
+fn fetch(
+    cfg: &JorupConfig,
+    client: &mut Client,
+    cache: &MetadataCache,
+    release: &Release,
+    allow_unverified: bool,
+) -> Result<(), Error> {
+    // the directory of a new release was just created by `Release::new`
+    let fresh = std::fs::read_dir(release.dir())
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(false);
+
+    let result = fetch_release(cfg, client, cache, release, allow_unverified);
+    // nothing is left of a failed new install, unless its download can be
+    // resumed
+    if result.is_err() && fresh && !download::can_resume(&release.get_asset()) {
+        let _ = std::fs::remove_dir_all(release.dir());
+    }
+    result
+}
+
+fn fetch_release(
+    cfg: &JorupConfig,
+    client: &mut Client,
+    cache: &MetadataCache,
+    release: &Release,
+    allow_unverified: bool,
+) -> Result<(), Error> {
+    if cfg.offline() && release.asset_need_fetched() {
+        return Err(Error::Offline);
+    }
 
-    let asset = release
-        .asset_remote(&mut client)
-        .map_err(Error::ReleaseLoad)?;
+    // the remote asset is not needed to install from an already downloaded
+    // archive while offline
+    let asset = if cfg.offline() {
+        None
+    } else {
+        Some(
+            release
+                .asset_remote(client, cache, cfg.release_source())
+                .map_err(Error::ReleaseLoad)?,
+        )
+    };
 
-    if release.asset_need_fetched() {
+    if let (Some(asset), true) = (&asset, release.asset_need_fetched()) {
         client
             .download_file(
                 &release.get_asset().display().to_string(),
-                &asset.as_ref(),
+                asset.url(),
                 release.get_asset(),
             )
             .map_err(Error::CannotUpdate)?;
         println!("**** asset downloaded");
     }
 
-    release.asset_open().map_err(Error::ReleaseLoad)?;
+    if let Some(checksum_url) = asset.as_ref().and_then(AssetRemote::checksum_url) {
+        client
+            .download_file(
+                &release.get_asset_checksum().display().to_string(),
+                checksum_url,
+                release.get_asset_checksum(),
+            )
+            .map_err(Error::CannotUpdate)?;
+    }
 
-    if make_default {
-        release.make_default(&cfg).map_err(Error::ReleaseLoad)?;
+    if release.get_asset_checksum().is_file() {
+        if let Err(err) = release.asset_verify() {
+            // do not keep a corrupted asset around, it would be
+            // considered as already fetched by the next install
+            let _ = std::fs::remove_file(release.get_asset());
+            let _ = std::fs::remove_file(release.get_asset_checksum());
+            return Err(Error::ReleaseLoad(err));
+        }
+        println!("**** asset verified");
+    } else if allow_unverified {
+        eprintln!("WARN: no checksum published for this release, the asset is not verified");
+    } else {
+        return Err(Error::ReleaseLoad(ReleaseError::NoChecksum));
     }
 
+    release.asset_open().map_err(Error::ReleaseLoad)?;
+
     Ok(())
 }
 
-fn list(cfg: JorupConfig) -> Result<(), Error> {
-    for release in list_installed_releases(&cfg).map_err(Error::ReleasesList)? {
-        println!("{}", release);
+fn list(mut cfg: JorupConfig) -> Result<(), Error> {
+    let default = Release::load_default(&mut cfg)
+        .map_err(Error::ReleasesList)?
+        .map(|release| release.version().clone());
+    // the list of releases is still useful without the blockchains
+    let blockchains = cfg
+        .load_jor()
+        .map(|jor| jor.blockchains().to_vec())
+        .unwrap_or_default();
+
+    let mut versions: Vec<_> = list_installed_releases(&cfg)
+        .map_err(Error::ReleasesList)?
+        .collect();
+    versions.sort_by(|a, b| b.cmp(a));
+
+    for version in versions {
+        let marker = if Some(&version) == default.as_ref() {
+            "*"
+        } else {
+            " "
+        };
+        let compatible: Vec<_> = blockchains
+            .iter()
+            .filter(|blockchain| blockchain.jormungandr_versions().matches(&version))
+            .map(|blockchain| blockchain.name())
+            .collect();
+        let release = Release::new(&mut cfg, version).map_err(Error::ReleaseLoad)?;
+        let installed_at = release
+            .installed_at()
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|_| "-".to_string());
+        let linked_dir = release.linked_dir();
+        // the linked directories do not belong to jorup
+        let size = match linked_dir {
+            Some(_) => "-".to_string(),
+            None => release
+                .disk_size()
+                .map(|size| HumanBytes(size).to_string())
+                .unwrap_or_else(|_| "-".to_string()),
+        };
+        let mut details = compatible.join(", ");
+        if let Some(dir) = linked_dir {
+            details = format!("-> {}  {}", dir.display(), details);
+        }
+
+        let line = format!(
+            "{} {:<24} {:<10} {:>10}  {}",
+            marker,
+            release.version().to_string(),
+            installed_at,
+            size,
+            details
+        );
+        println!("{}", line.trim_end());
     }
     Ok(())
 }
 
+fn default(mut cfg: JorupConfig, version_req: VersionReq) -> Result<(), Error> {
+    let release = Release::load_matching(&mut cfg, &version_req).map_err(Error::ReleaseLoad)?;
+    release.make_default(&cfg).map_err(Error::ReleaseLoad)?;
+    println!("{} is now the default release", release.version());
+    Ok(())
+}
+
 fn remove(mut cfg: JorupConfig, version: Version) -> Result<(), Error> {
     let version_req = VersionReq::exact(version);
-    let release = Release::load(&mut cfg, &version_req).map_err(Error::ReleaseLoad)?;
+    let release = Release::load_matching(&mut cfg, &version_req).map_err(Error::ReleaseLoad)?;
+    if release.linked_dir().is_some() {
+        return Err(Error::Linked(release.version().clone()));
+    }
     std::fs::remove_dir_all(release.dir()).map_err(Error::RemoveRelease)?;
 
+    unset_default(&cfg, release.version()).map_err(Error::UnsetDefault)
+}
+
+fn build(
+    mut cfg: JorupConfig,
+    path: PathBuf,
+    name: Option<String>,
+    make_default: bool,
+) -> Result<(), Error> {
+    let path = std::fs::canonicalize(&path).map_err(|e| Error::Checkout(e, path))?;
+    let name = match name {
+        Some(name) => name,
+        None => path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+    };
+    let version = Version::custom(&name).ok_or(Error::InvalidName(name))?;
+    // do not overwrite the binaries of a linked directory
+    if std::fs::read_link(cfg.release_dir().join(version.to_string())).is_ok() {
+        return Err(Error::Linked(version));
+    }
+
+    println!("**** building {}", path.display());
+    // the target directory may be moved by the environment or the cargo
+    // configuration of the checkout, cargo reports where the binaries are
+    let mut cargo = Process::new("cargo")
+        .args([
+            "build",
+            "--release",
+            "--message-format=json-render-diagnostics",
+        ])
+        .current_dir(&path)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(Error::BuildCommand)?;
+    let mut binaries = Vec::new();
+    for line in BufReader::new(cargo.stdout.take().unwrap()).lines() {
+        let message: serde_json::Value = match line {
+            Ok(line) => serde_json::from_str(&line).unwrap_or_default(),
+            Err(_) => break,
+        };
+        if message["reason"] != "compiler-artifact" {
+            continue;
+        }
+        let tool = PROXIES
+            .iter()
+            .find(|tool| message["target"]["name"] == **tool);
+        if let (Some(tool), Some(executable)) = (tool, message["executable"].as_str()) {
+            binaries.push((*tool, PathBuf::from(executable)));
+        }
+    }
+    let status = cargo.wait().map_err(Error::BuildCommand)?;
+    if !status.success() {
+        return Err(Error::BuildFailed(status));
+    }
+
+    if let Some(tool) = PROXIES
+        .iter()
+        .find(|tool| !binaries.iter().any(|(name, _)| name == *tool))
+    {
+        return Err(Error::MissingBinary(tool));
+    }
+
+    let release = Release::new(&mut cfg, version).map_err(Error::ReleaseLoad)?;
+    for (tool, binary) in binaries {
+        std::fs::copy(&binary, release.get_tool(tool))
+            .map_err(|e| Error::CannotCopyBinary(e, binary))?;
+    }
+    println!("**** {} registered", release.version());
+
+    if make_default {
+        release.make_default(&cfg).map_err(Error::ReleaseLoad)?;
+    }
+
+    Ok(())
+}
+
+fn link(mut cfg: JorupConfig, name: String, dir: PathBuf) -> Result<(), Error> {
+    let version = Version::custom(&name).ok_or(Error::InvalidName(name))?;
+    let dir = std::fs::canonicalize(&dir).map_err(|e| Error::LinkDir(e, dir))?;
+    let release = Release::link(&mut cfg, version, &dir).map_err(Error::Link)?;
+    println!("{} is now linked to {}", release.version(), dir.display());
+    Ok(())
+}
+
+fn unlink(cfg: JorupConfig, version: Version) -> Result<(), Error> {
+    Release::unlink(&cfg, version).map_err(Error::Link)
+}
+
+fn prune(mut cfg: JorupConfig, keep_archives: bool, dry_run: bool) -> Result<(), Error> {
+    let installed: Vec<_> = list_installed_releases(&cfg)
+        .map_err(Error::ReleasesList)?
+        .collect();
+
+    // custom releases cannot be downloaded again
+    let mut in_use: Vec<_> = installed
+        .iter()
+        .filter(|version| matches!(version, Version::Custom(_)))
+        .cloned()
+        .collect();
+    if let Some(release) = Release::load_default(&mut cfg).map_err(Error::ReleaseLoad)? {
+        in_use.push(release.version().clone());
+    }
+
+    let mut version_reqs: Vec<_> = cfg
+        .load_jor()
+        .map_err(Error::Jorfile)?
+        .blockchains()
+        .iter()
+        .map(|blockchain| blockchain.jormungandr_versions().clone())
+        .collect();
+    for version in overrides::list(&cfg).map_err(Error::Overrides)?.values() {
+        // an invalid override does not pin anything
+        if let Ok(version_req) = VersionReq::parse_exact(version) {
+            version_reqs.push(version_req);
+        }
+    }
+    if let Some(version_override) = Override::find(&cfg).map_err(Error::Overrides)? {
+        version_reqs.push(version_override.version_req().clone());
+    }
+    for version_req in version_reqs {
+        let best = installed
+            .iter()
+            .filter(|version| version_req.matches(version))
+            .max();
+        if let Some(version) = best {
+            in_use.push(version.clone());
+        }
+    }
+
+    let mut reclaimed = 0;
+    for version in installed {
+        let keep = in_use.contains(&version);
+        let release = Release::new(&mut cfg, version).map_err(Error::ReleaseLoad)?;
+
+        if !keep {
+            reclaimed += release.disk_size().map_err(Error::RemoveRelease)?;
+            println!("removing release {}", release.version());
+            if !dry_run {
+                std::fs::remove_dir_all(release.dir()).map_err(Error::RemoveRelease)?;
+            }
+        } else if !keep_archives && !release.asset_need_open() {
+            for archive in &[release.get_asset(), release.get_asset_checksum()] {
+                if let Ok(metadata) = archive.metadata() {
+                    reclaimed += metadata.len();
+                    println!("removing {}", archive.display());
+                    if !dry_run {
+                        std::fs::remove_file(archive).map_err(Error::RemoveRelease)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if dry_run {
+        println!("{} would be reclaimed", HumanBytes(reclaimed));
+    } else {
+        println!("{} reclaimed", HumanBytes(reclaimed));
+    }
     Ok(())
 }
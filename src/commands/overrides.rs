@@ -0,0 +1,99 @@
+use crate::{
+    common::JorupConfig,
+    utils::{
+        overrides::{self, Override, OVERRIDE_ENV, OVERRIDE_FILE},
+        version::VersionReq,
+    },
+};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use thiserror::Error;
+
+/// Pin the version of jormungandr used in a directory
+///
+/// The version used by `jorup run` and `jorup wallet` when none is given
+/// with `-v` is, in order of precedence: the `JORUP_VERSION` environment
+/// variable, the override set with this command for the current directory
+/// or one of its parents, the content of the first `jorup-toolchain` file
+/// found in the current directory or its parents, and finally the latest
+/// release compatible with the blockchain.
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Set the version of jormungandr to use in a directory
+    Set {
+        /// A version or a release channel
+        #[structopt(parse(try_from_str = VersionReq::parse_exact))]
+        version: VersionReq,
+
+        /// The directory to set the override for, defaults to the current
+        /// directory
+        #[structopt(long)]
+        path: Option<PathBuf>,
+    },
+    /// Remove the override of a directory
+    Unset {
+        /// The directory to remove the override of, defaults to the current
+        /// directory
+        #[structopt(long)]
+        path: Option<PathBuf>,
+    },
+    /// List the overrides and show the one in effect in the current directory
+    List,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Cannot find the directory {1}")]
+    Canonicalize(#[source] std::io::Error, PathBuf),
+    #[error("Cannot get the current directory")]
+    CurrentDir(#[source] std::io::Error),
+    #[error(transparent)]
+    Overrides(#[from] overrides::Error),
+    #[error("No override set for {0}")]
+    NoOverride(PathBuf),
+}
+
+impl Command {
+    pub fn run(self, cfg: JorupConfig) -> Result<(), Error> {
+        match self {
+            Command::Set { version, path } => {
+                let dir = override_dir(path)?;
+                overrides::set(&cfg, &dir, &version)?;
+                println!("{} is now using {}", dir.display(), version);
+            }
+            Command::Unset { path } => {
+                let dir = override_dir(path)?;
+                if !overrides::unset(&cfg, &dir)? {
+                    return Err(Error::NoOverride(dir));
+                }
+            }
+            Command::List => {
+                for (dir, version) in overrides::list(&cfg)? {
+                    println!("{}\t{}", dir.display(), version);
+                }
+                match Override::find(&cfg)? {
+                    Some(version_override) => println!(
+                        "\nIn effect: {} (from {})",
+                        version_override.version_req(),
+                        version_override.source()
+                    ),
+                    None => println!(
+                        "\nNo override in effect, set one with `jorup override set`, \
+                         a `{}` file or the {} environment variable",
+                        OVERRIDE_FILE, OVERRIDE_ENV
+                    ),
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn override_dir(path: Option<PathBuf>) -> Result<PathBuf, Error> {
+    let path = match path {
+        Some(path) => path,
+        None => std::env::current_dir().map_err(Error::CurrentDir)?,
+    };
+    std::fs::canonicalize(&path).map_err(|e| Error::Canonicalize(e, path))
+}
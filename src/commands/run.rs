@@ -1,6 +1,11 @@
 use crate::{
     common::JorupConfig,
-    utils::{blockchain::Blockchain, release::Release, runner::RunnerControl, version::Version},
+    utils::{
+        blockchain::Blockchain,
+        release::Release,
+        runner::{Restart, RestartPolicy, RunnerControl},
+        version::VersionReq,
+    },
 };
 use std::{
     net::{IpAddr, Ipv4Addr, SocketAddr},
@@ -13,17 +18,37 @@ use thiserror::Error;
 #[derive(Debug, StructOpt)]
 pub struct Command {
     /// The blockchain to run jormungandr for
-    blockchain: String,
+    ///
+    /// Defaults to the `default-blockchain` setting.
+    blockchain: Option<String>,
 
-    /// The version of Jormungandr to run. If not specified, the latest
-    /// compatible version will be used.
-    #[structopt(short, long)]
-    version: Option<Version>,
+    /// The version of Jormungandr to run, or a release channel: `stable`,
+    /// `rc`, `beta`, `latest` or `nightly`, or the name of a custom release
+    /// (see `jorup node build` and `jorup node link`). If not specified, the
+    /// version set with `jorup override` or the latest compatible version
+    /// will be used.
+    #[structopt(short, long, parse(try_from_str = VersionReq::parse_exact))]
+    version: Option<VersionReq>,
 
     /// Run the node as a daemon
     #[structopt(long)]
     daemon: bool,
 
+    /// Restart the daemon when it exits: `on-failure` when it exits with an
+    /// error or is killed, or `always`
+    ///
+    /// The node is then started by a jorup process in the background, which
+    /// waits longer between every restart in a row, from 1 second up to 5
+    /// minutes, and logs to `SUPERVISOR.logs` in the directory of the
+    /// blockchain.
+    #[structopt(long, requires = "daemon")]
+    restart: Option<RestartPolicy>,
+
+    /// With `--restart`, the number of restarts in a row after which the node
+    /// is left stopped. A node running for 10 minutes resets the count.
+    #[structopt(long, default_value = "5")]
+    max_restarts: u32,
+
     /// Provide a custom configuration file to the node.
     ///
     /// Note that when using this flag `jorup` will not provide any
@@ -40,11 +65,6 @@ pub struct Command {
     #[structopt(long)]
     rest_listen: Option<SocketAddr>,
 
-    /// The directory containing jormungandr and jcli, can be useful for
-    /// development purposes. When provided, the `--version` flag is ignored.
-    #[structopt(long)]
-    bin: Option<PathBuf>,
-
     /// Extra parameters to pass on to the node
     ///
     /// Add pass on extra parameters to jormungandr for example, this command
@@ -74,28 +94,23 @@ pub enum Error {
 impl Command {
     pub fn run(self, mut cfg: JorupConfig) -> Result<(), Error> {
         // prepare entry directory
-        let blockchain =
-            Blockchain::load(&mut cfg, &self.blockchain).map_err(Error::NoValidBlockchain)?;
+        let blockchain = Blockchain::load_or_default(&mut cfg, self.blockchain.as_deref())
+            .map_err(Error::NoValidBlockchain)?;
         blockchain.prepare().map_err(Error::NoValidBlockchain)?;
 
-        let bin = if let Some(dir) = self.bin {
-            eprintln!("WARN: using custom binaries from {}", dir.display());
-            dir
+        let release = if let Some(version_req) = self.version {
+            Release::load_matching(&mut cfg, &version_req)
         } else {
-            let release = if let Some(version) = self.version {
-                Release::new(&mut cfg, version)
-            } else {
-                Release::load(&mut cfg, blockchain.jormungandr_version_req())
-            }
-            .map_err(Error::NoCompatibleRelease)?;
+            Release::load(&mut cfg, blockchain.jormungandr_version_req())
+        }
+        .map_err(Error::NoCompatibleRelease)?;
 
-            if release.asset_need_fetched() {
-                // asset release is not available
-                return Err(Error::NoCompatibleBinaries);
-            }
+        if release.asset_need_fetched() {
+            // asset release is not available
+            return Err(Error::NoCompatibleBinaries);
+        }
 
-            release.dir().clone()
-        };
+        let bin = release.dir().clone();
 
         let mut runner =
             RunnerControl::new(&blockchain, bin).map_err(Error::CannotStartRunnerController)?;
@@ -124,7 +139,7 @@ impl Command {
                     if default_config {
                         Some(SocketAddr::new(
                             IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)),
-                            8080,
+                            cfg.rest_port(),
                         ))
                     } else {
                         None
@@ -134,8 +149,13 @@ impl Command {
         };
 
         if self.daemon {
+            let max_restarts = self.max_restarts;
+            let restart = self.restart.map(|policy| Restart {
+                policy,
+                max_restarts,
+            });
             runner
-                .spawn(default_config, rest_addr, extra)
+                .spawn(default_config, rest_addr, extra, cfg.keep_logs(), restart)
                 .map_err(Error::Start)
         } else {
             runner
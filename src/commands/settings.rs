@@ -0,0 +1,67 @@
+use crate::{
+    common::JorupConfig,
+    settings::{self, Settings, KEYS},
+};
+use structopt::StructOpt;
+use thiserror::Error;
+
+/// Manage the settings file of jorup
+///
+/// The settings are read from the command line, then from the `JORUP_*`
+/// environment variables (for example `JORUP_REST_PORT` for `rest-port`) and
+/// finally from the `settings.toml` file in the jorup home.
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Print the effective value of a setting
+    Get { key: String },
+    /// Set a setting in the settings file
+    Set { key: String, value: String },
+    /// Remove a setting from the settings file
+    Unset { key: String },
+    /// List the effective settings and where they come from
+    List,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Cannot read the settings")]
+    Read(#[source] settings::Error),
+    #[error("Cannot update the settings file")]
+    Write(#[source] settings::Error),
+    #[error("Setting '{0}' is not set")]
+    NotSet(String),
+}
+
+impl Command {
+    pub fn run(self, cfg: JorupConfig) -> Result<(), Error> {
+        match self {
+            Command::Get { key } => match cfg.setting(&key).map_err(Error::Read)? {
+                Some((value, _)) => println!("{}", value),
+                None => return Err(Error::NotSet(key)),
+            },
+            Command::Set { key, value } => update(&cfg, &key, Some(&value))?,
+            Command::Unset { key } => update(&cfg, &key, None)?,
+            Command::List => {
+                for key in KEYS {
+                    if let Some((value, origin)) = cfg.setting(key).map_err(Error::Read)? {
+                        let value = if *key == "github-token" {
+                            "********".to_string()
+                        } else {
+                            value
+                        };
+                        println!("{} = {}\t({})", key, value, origin);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn update(cfg: &JorupConfig, key: &str, value: Option<&str>) -> Result<(), Error> {
+    let path = cfg.settings_file();
+    let mut settings = Settings::load(&path).map_err(Error::Read)?;
+    settings.set(key, value).map_err(Error::Write)?;
+    settings.save(&path).map_err(Error::Write)
+}
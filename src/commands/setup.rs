@@ -1,19 +1,34 @@
 use super::Cmd;
-use crate::common::JorupConfig;
+use crate::{
+    common::JorupConfig,
+    utils::{
+        blockchain,
+        cache::MetadataCache,
+        download, github,
+        release::{self, PROXIES, TARGET},
+        runner::{self, RunnerInfo},
+        version::Version,
+    },
+};
 use std::{
     env::{self, consts::EXE_SUFFIX},
     fs, io,
     path::{Path, PathBuf},
+    time::Duration,
 };
 use structopt::StructOpt;
 use thiserror::Error;
 
+/// How long to wait for the running nodes to stop on uninstall before
+/// killing them
+const NODE_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Operations for 'jorup'
 #[derive(Debug, StructOpt)]
 pub enum Command {
     Install(Install),
-    Update,
-    Uninstall,
+    Update(Update),
+    Uninstall(Uninstall),
 }
 
 /// Install jorup
@@ -29,6 +44,37 @@ pub struct Install {
     force: bool,
 }
 
+/// Update jorup to the latest version
+#[derive(Debug, StructOpt)]
+pub struct Update {
+    /// Only check whether a new version of jorup is available, do not
+    /// install it
+    #[structopt(long)]
+    check: bool,
+
+    /// Install a particular version of jorup. This can be used to pin or to
+    /// downgrade the installed version.
+    #[structopt(short, long)]
+    version: Option<Version>,
+}
+
+/// Uninstall jorup, stop the running nodes and remove all the installed
+/// releases and blockchains
+#[derive(Debug, StructOpt)]
+pub struct Uninstall {
+    /// Keep the blockchain data (node storage, secrets and wallet keys)
+    #[structopt(long)]
+    keep_data: bool,
+
+    /// Only list what would be stopped and removed
+    #[structopt(long)]
+    dry_run: bool,
+
+    /// Do not ask for confirmation
+    #[structopt(short, long)]
+    yes: bool,
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error(transparent)]
@@ -39,6 +85,31 @@ pub enum Error {
     NoInstallerExecutable(#[source] io::Error),
     #[error("Cannot install jorup in {1}")]
     Install(#[source] io::Error, PathBuf),
+    #[error("Cannot run this command offline")]
+    Offline,
+    #[error("Failed to create the downloader client")]
+    DownloaderCreate(#[source] download::Error),
+    #[error("Cannot find a jorup release on GitHub")]
+    GitHub(#[source] github::Error),
+    #[error("No jorup {0} binary available for {1}")]
+    AssetNotFound(String, String),
+    #[cfg(windows)]
+    #[error("Download and run jorup-init.exe of jorup {0} to update jorup on Windows")]
+    UpdateWithInstaller(String),
+    #[error("Cannot download jorup {0}")]
+    Download(#[source] download::Error, String),
+    #[error("Cannot replace the jorup executable {1}")]
+    Replace(#[source] io::Error, PathBuf),
+    #[error("Cannot update the jormungandr and jcli proxies")]
+    Proxies(#[source] release::Error),
+    #[error("Uninstall aborted")]
+    Aborted,
+    #[error("Cannot stop the node running for {1}")]
+    StopNode(#[source] runner::Error, String),
+    #[error("Cannot list the content of {1}")]
+    ReadDir(#[source] io::Error, PathBuf),
+    #[error("{0} path(s) could not be removed, see above")]
+    NotRemoved(usize),
     #[cfg(unix)]
     #[error("Cannot set permissions for {1}")]
     Permissions(#[source] io::Error, PathBuf),
@@ -57,8 +128,8 @@ impl Command {
     pub fn run(self, cfg: JorupConfig) -> Result<(), Error> {
         match self {
             Command::Install(cmd) => cmd.run(cfg),
-            Command::Update => update(cfg),
-            Command::Uninstall => uninstall(cfg),
+            Command::Update(cmd) => cmd.run(cfg),
+            Command::Uninstall(cmd) => cmd.run(cfg),
         }
     }
 }
@@ -83,6 +154,7 @@ impl Install {
         let jorup_current = env::current_exe().map_err(Error::NoInstallerExecutable)?;
         fs::copy(&jorup_current, &jorup_file).map_err(|e| Error::Install(e, jorup_file.clone()))?;
         make_executable(&jorup_file)?;
+        refresh_proxies(&cfg)?;
 
         if !self.no_modify_path {
             do_add_to_path(&cfg)?;
@@ -96,17 +168,251 @@ impl Cmd for Install {
     type Err = Error;
 
     fn run(self) -> Result<(), Self::Err> {
-        let cfg = crate::common::JorupConfig::new(None, None, false)?;
+        let cfg = crate::common::JorupConfig::new(None, None, Default::default())?;
         self.run(cfg)
     }
 }
 
-pub fn uninstall(_cfg: JorupConfig) -> Result<(), Error> {
-    unimplemented!()
+impl Uninstall {
+    pub fn run(self, cfg: JorupConfig) -> Result<(), Error> {
+        let to_remove = if self.keep_data {
+            let home_dir = cfg.home_dir().clone();
+            let blockchain_dir = cfg.blockchain_dir();
+            fs::read_dir(&home_dir)
+                .map_err(|e| Error::ReadDir(e, home_dir.clone()))?
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path != &blockchain_dir)
+                .collect()
+        } else {
+            vec![cfg.home_dir().clone()]
+        };
+
+        if !self.dry_run && !self.yes {
+            let confirmed = dialoguer::Confirmation::new()
+                .with_text(&format!(
+                    "Uninstall jorup and remove {}?",
+                    cfg.home_dir().display()
+                ))
+                .interact()
+                .unwrap();
+            if !confirmed {
+                return Err(Error::Aborted);
+            }
+        }
+
+        stop_nodes(&cfg, self.dry_run)?;
+
+        // the PATH entries are removed first, so that they do not outlive a
+        // partial removal of the files
+        do_remove_from_path(&cfg, self.dry_run)?;
+
+        if self.dry_run {
+            for path in to_remove {
+                println!("would remove {}", path.display());
+            }
+            return Ok(());
+        }
+
+        let running = running_executable();
+        let mut failed = 0;
+        for path in to_remove {
+            failed += remove(&path, running.as_deref());
+        }
+        if failed > 0 {
+            return Err(Error::NotRemoved(failed));
+        }
+        Ok(())
+    }
+}
+
+/// Remove a file or a directory with all its content, keeping going when
+/// something cannot be removed. The failures are reported as they happen and
+/// their number is returned.
+///
+/// The `running` executable, and the directories containing it, are left
+/// behind.
+fn remove(path: &Path, running: Option<&Path>) -> usize {
+    let contains_running = match running {
+        Some(running) => fs::canonicalize(path)
+            .map(|path| running.starts_with(path))
+            .unwrap_or(false),
+        None => false,
+    };
+    let is_dir = path.symlink_metadata().map(|m| m.is_dir()).unwrap_or(false);
+
+    if !is_dir {
+        if contains_running {
+            println!("{} is running, remove it once it exited", path.display());
+            return 0;
+        }
+        // the links to directories are removed with `remove_dir_all`, which
+        // does not follow them
+        let result = if path.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        return match result {
+            Ok(()) => 0,
+            Err(err) => {
+                eprintln!("ERROR: cannot remove {}: {}", path.display(), err);
+                1
+            }
+        };
+    }
+
+    let failed = match fs::read_dir(path) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| remove(&entry.path(), running))
+            .sum(),
+        Err(err) => {
+            eprintln!(
+                "ERROR: cannot list the content of {}: {}",
+                path.display(),
+                err
+            );
+            return 1;
+        }
+    };
+    if failed > 0 || contains_running {
+        return failed;
+    }
+
+    match fs::remove_dir(path) {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("ERROR: cannot remove {}: {}", path.display(), err);
+            1
+        }
+    }
+}
+
+/// The running jorup executable, if it cannot be removed while running
+#[cfg(windows)]
+fn running_executable() -> Option<PathBuf> {
+    env::current_exe().and_then(fs::canonicalize).ok()
+}
+
+#[cfg(unix)]
+fn running_executable() -> Option<PathBuf> {
+    None
+}
+
+/// Stop the nodes of every blockchain directory with a runner file, whether
+/// or not the blockchain is still known by the jorfile. The removal is
+/// aborted if a runner file cannot be read, as its node could be left running.
+fn stop_nodes(cfg: &JorupConfig, dry_run: bool) -> Result<(), Error> {
+    let runner_files =
+        blockchain::runner_files(cfg).map_err(|e| Error::ReadDir(e, cfg.blockchain_dir()))?;
+
+    for (name, runner_file) in runner_files {
+        let info = RunnerInfo::load(&runner_file).map_err(|e| Error::StopNode(e, name.clone()))?;
+        if !info
+            .is_running()
+            .map_err(|e| Error::StopNode(e, name.clone()))?
+        {
+            continue;
+        }
+        if dry_run {
+            println!("would stop the node running for {}", name);
+            continue;
+        }
+        // the node would not survive the removal of its release anyway
+        info.shutdown(&runner_file, NODE_SHUTDOWN_TIMEOUT, true)
+            .map_err(|e| Error::StopNode(e, name.clone()))?;
+        println!("stopped the node running for {}", name);
+    }
+
+    Ok(())
+}
+
+impl Update {
+    pub fn run(self, cfg: JorupConfig) -> Result<(), Error> {
+        if cfg.offline() {
+            return Err(Error::Offline);
+        }
+
+        let current = Version::parse(env!("CARGO_PKG_VERSION")).unwrap();
+        let pinned = self.version.is_some();
+
+        let mut client = cfg.client().map_err(Error::DownloaderCreate)?;
+        let cache = MetadataCache::new(&cfg);
+        let release =
+            github::find_jorup_release(&mut client, &cache, self.version).map_err(Error::GitHub)?;
+        let version = release.version().clone();
+
+        if self.check {
+            if version > current {
+                println!("jorup {} is available (installed: {})", version, current);
+            } else {
+                println!("jorup is up to date ({})", current);
+            }
+            return Ok(());
+        }
+
+        if version == current || (!pinned && version < current) {
+            println!("jorup is up to date ({})", current);
+            return Ok(());
+        }
+
+        update_executable(&cfg, &mut client, &release)?;
+        refresh_proxies(&cfg)?;
+
+        println!("jorup updated from {} to {}", current, version);
+
+        Ok(())
+    }
+}
+
+/// Download the jorup executable of the release and move it into place
+#[cfg(unix)]
+fn update_executable(
+    cfg: &JorupConfig,
+    client: &mut download::Client,
+    release: &github::Release,
+) -> Result<(), Error> {
+    let version = release.version();
+    let asset_name = format!("jorup-{}-{}", version.to_git_tag(), TARGET);
+    let url = release
+        .get_asset_url_by_name(&asset_name)
+        .ok_or_else(|| Error::AssetNotFound(version.to_string(), TARGET.to_string()))?
+        .to_string();
+
+    let bin_dir = cfg.bin_dir();
+    let jorup_file = bin_dir.join("jorup");
+    // download next to the final location so that the new executable can be
+    // moved into place with a single rename
+    let jorup_new = bin_dir.join("jorup.new");
+
+    client
+        .download_file(&asset_name, &url, &jorup_new)
+        .map_err(|e| Error::Download(e, version.to_string()))?;
+    make_executable(&jorup_new)?;
+    fs::rename(&jorup_new, &jorup_file).map_err(|e| Error::Replace(e, jorup_file))
 }
 
-pub fn update(_cfg: JorupConfig) -> Result<(), Error> {
-    unimplemented!()
+/// The Windows releases only ship the `jorup-init.exe` installer
+#[cfg(windows)]
+fn update_executable(
+    _cfg: &JorupConfig,
+    _client: &mut download::Client,
+    release: &github::Release,
+) -> Result<(), Error> {
+    Err(Error::UpdateWithInstaller(release.version().to_string()))
+}
+
+/// Reinstall the proxies, if any, to point them to the new jorup executable
+fn refresh_proxies(cfg: &JorupConfig) -> Result<(), Error> {
+    let installed = PROXIES.iter().any(|tool| {
+        let proxy = cfg.bin_dir().join(format!("{}{}", tool, EXE_SUFFIX));
+        proxy.symlink_metadata().is_ok()
+    });
+    if installed {
+        release::install_proxies(cfg).map_err(Error::Proxies)?;
+    }
+    Ok(())
 }
 
 #[cfg(unix)]
@@ -158,13 +464,31 @@ fn do_add_to_path(cfg: &JorupConfig) -> Result<(), Error> {
     Ok(())
 }
 
+#[cfg(unix)]
+fn do_remove_from_path(cfg: &JorupConfig, dry_run: bool) -> Result<(), Error> {
+    let addition = format!("\n{}", shell_export_string(cfg)?);
+
+    for rcpath in get_add_path_methods() {
+        if !rcpath.is_file() {
+            continue;
+        }
+        let file = fs::read_to_string(&rcpath).map_err(|e| Error::Read(e, rcpath.clone()))?;
+        if !file.contains(&addition) {
+            continue;
+        }
+        if dry_run {
+            println!("would remove the PATH entry from {}", rcpath.display());
+            continue;
+        }
+        fs::write(&rcpath, file.replace(&addition, ""))
+            .map_err(|e| Error::Write(e, rcpath.clone()))?;
+    }
+
+    Ok(())
+}
+
 #[cfg(windows)]
 fn do_add_to_path(cfg: &JorupConfig) -> Result<(), Error> {
-    use std::ptr;
-    use winapi::shared::minwindef::*;
-    use winapi::um::winuser::{
-        SendMessageTimeoutA, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
-    };
     use winreg::enums::*;
     use winreg::RegKey;
 
@@ -185,6 +509,57 @@ fn do_add_to_path(cfg: &JorupConfig) -> Result<(), Error> {
         .set_value("Path", &new_path)
         .map_err(Error::WinregError)?;
 
+    broadcast_environment_change();
+
+    Ok(())
+}
+
+#[cfg(windows)]
+fn do_remove_from_path(cfg: &JorupConfig, dry_run: bool) -> Result<(), Error> {
+    use winreg::enums::*;
+    use winreg::RegKey;
+
+    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+    let environment = hkcu
+        .open_subkey_with_flags("Environment", KEY_READ | KEY_WRITE)
+        .map_err(Error::WinregError)?;
+
+    let current_path: String = environment.get_value("Path").map_err(Error::WinregError)?;
+    let jorup_path = cfg.bin_dir().display().to_string();
+
+    if !current_path.split(';').any(|path| path == jorup_path) {
+        return Ok(());
+    }
+
+    if dry_run {
+        println!("would remove {} from the user's Path", jorup_path);
+        return Ok(());
+    }
+
+    let new_path = current_path
+        .split(';')
+        .filter(|path| *path != jorup_path)
+        .collect::<Vec<_>>()
+        .join(";");
+    environment
+        .set_value("Path", &new_path)
+        .map_err(Error::WinregError)?;
+
+    broadcast_environment_change();
+
+    Ok(())
+}
+
+/// Notify the running applications that the environment has changed so that
+/// the new Path is picked up without logging out.
+#[cfg(windows)]
+fn broadcast_environment_change() {
+    use std::ptr;
+    use winapi::shared::minwindef::*;
+    use winapi::um::winuser::{
+        SendMessageTimeoutA, HWND_BROADCAST, SMTO_ABORTIFHUNG, WM_SETTINGCHANGE,
+    };
+
     unsafe {
         SendMessageTimeoutA(
             HWND_BROADCAST,
@@ -196,8 +571,6 @@ fn do_add_to_path(cfg: &JorupConfig) -> Result<(), Error> {
             ptr::null_mut(),
         );
     }
-
-    Ok(())
 }
 
 /// Decide which rcfiles we're going to update, so we can tell the user before
@@ -2,14 +2,29 @@ use crate::{
     common::JorupConfig,
     utils::{blockchain::Blockchain, runner::RunnerControl},
 };
+use std::time::Duration;
 use structopt::StructOpt;
 use thiserror::Error;
 
 /// Stop jormungandr
+///
+/// The node is asked to shut down through its REST API, if enabled, and is
+/// sent SIGTERM if it is still running after the timeout.
 #[derive(Debug, StructOpt)]
 pub struct Command {
     /// The blockchain to run jormungandr for
-    blockchain: String,
+    ///
+    /// Defaults to the `default-blockchain` setting.
+    blockchain: Option<String>,
+
+    /// How many seconds to wait for the node to stop, first after asking it
+    /// through the REST API, then after sending it SIGTERM
+    #[structopt(long, default_value = "30")]
+    timeout: u64,
+
+    /// Kill the node if it is still running after SIGTERM
+    #[structopt(long)]
+    force: bool,
 }
 
 #[derive(Debug, Error)]
@@ -25,13 +40,15 @@ pub enum Error {
 impl Command {
     pub fn run(self, mut cfg: JorupConfig) -> Result<(), Error> {
         // prepare entry directory
-        let blockchain =
-            Blockchain::load(&mut cfg, &self.blockchain).map_err(Error::NoValidBlockchain)?;
+        let blockchain = Blockchain::load_or_default(&mut cfg, self.blockchain.as_deref())
+            .map_err(Error::NoValidBlockchain)?;
         blockchain.prepare().map_err(Error::NoValidBlockchain)?;
 
         let mut runner =
             RunnerControl::load(&blockchain).map_err(Error::CannotStartRunnerController)?;
 
-        runner.shutdown().map_err(Error::ShutdownError)
+        runner
+            .shutdown(Duration::from_secs(self.timeout), self.force)
+            .map_err(Error::ShutdownError)
     }
 }
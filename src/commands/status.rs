@@ -0,0 +1,147 @@
+use crate::{
+    commands::info::format_uptime,
+    common::JorupConfig,
+    utils::{
+        blockchain,
+        rest::RestClient,
+        runner::{self, RunnerInfo},
+    },
+};
+use chrono::Utc;
+use std::{io, path::PathBuf};
+use structopt::StructOpt;
+use thiserror::Error;
+
+/// Show the nodes running in the background, for every blockchain
+///
+/// Runner files left behind by nodes that did not shut down properly are
+/// flagged as stale, they are removed by the next `jorup run`.
+#[derive(Debug, StructOpt)]
+pub struct Command {}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Cannot read the blockchains directory {1}")]
+    BlockchainDir(#[source] io::Error, PathBuf),
+    #[error("Cannot check if the node of {1} is running")]
+    Runner(#[source] runner::Error, String),
+}
+
+impl Command {
+    pub fn run(self, cfg: JorupConfig) -> Result<(), Error> {
+        let runner_files = blockchain::runner_files(&cfg)
+            .map_err(|e| Error::BlockchainDir(e, cfg.blockchain_dir()))?;
+
+        if runner_files.is_empty() {
+            println!("No node running");
+            return Ok(());
+        }
+
+        println!(
+            "{:<16} {:>7} {:<24} {:>12} {:>5} {:>10}  STATE",
+            "BLOCKCHAIN", "PID", "RELEASE", "UPTIME", "REST", "TIP"
+        );
+        let mut stale = 0;
+        for (name, runner_file) in runner_files {
+            let info = match RunnerInfo::load(&runner_file) {
+                Ok(info) => info,
+                Err(err) => {
+                    eprintln!("WARN: {}: {}", name, err);
+                    continue;
+                }
+            };
+
+            let release = info
+                .jormungandr()
+                .parent()
+                .filter(|dir| dir.parent() == Some(&cfg.release_dir()))
+                .and_then(|dir| dir.file_name())
+                .map(|version| version.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "-".to_string());
+            let rest_port = info
+                .rest_port()
+                .map(|port| port.to_string())
+                .unwrap_or_else(|| "-".to_string());
+
+            if !info
+                .is_running()
+                .map_err(|e| Error::Runner(e, name.clone()))?
+            {
+                stale += 1;
+                // the supervisor gave up restarting the node
+                let state = match info
+                    .supervisor()
+                    .and_then(|supervisor| supervisor.last_exit_status())
+                {
+                    Some(status) => format!("stale, {}", status),
+                    None => "stale".to_string(),
+                };
+                println!(
+                    "{:<16} {:>7} {:<24} {:>12} {:>5} {:>10}  {}",
+                    name,
+                    info.pid(),
+                    release,
+                    "-",
+                    rest_port,
+                    "-",
+                    state
+                );
+                continue;
+            }
+
+            let node_running = info
+                .is_node_running()
+                .map_err(|e| Error::Runner(e, name.clone()))?;
+
+            let stats = info
+                .rest_port()
+                .filter(|_| node_running)
+                .and_then(|port| RestClient::new(port).ok())
+                .and_then(|rest| rest.stats().ok());
+            let uptime = info
+                .started_at()
+                .filter(|_| node_running)
+                .map(|started_at| (Utc::now() - started_at).num_seconds().max(0) as u64)
+                .or_else(|| stats.as_ref().and_then(|stats| stats.uptime))
+                .map(format_uptime)
+                .unwrap_or_else(|| "-".to_string());
+            let tip = stats
+                .as_ref()
+                .and_then(|stats| stats.last_block_height.clone())
+                .unwrap_or_else(|| "-".to_string());
+            let mut state = match &stats {
+                _ if !node_running => "restarting".to_string(),
+                Some(stats) => stats.state.clone(),
+                None if info.rest_port().is_some() => "REST unreachable".to_string(),
+                None => "REST disabled".to_string(),
+            };
+            if let Some(supervisor) = info.supervisor() {
+                if supervisor.restarts() > 0 {
+                    state.push_str(&format!(" (restarted {} times)", supervisor.restarts()));
+                }
+            }
+
+            println!(
+                "{:<16} {:>7} {:<24} {:>12} {:>5} {:>10}  {}",
+                name,
+                info.pid(),
+                release,
+                uptime,
+                rest_port,
+                tip,
+                state
+            );
+        }
+
+        if stale > 0 {
+            println!();
+            println!(
+                "{} stale runner file(s): the node did not shut down properly, \
+                 check the NODE.logs and SUPERVISOR.logs files of the blockchain",
+                stale
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use crate::utils::runner::{self, Restart, RestartPolicy, Supervision};
+use std::path::PathBuf;
+use structopt::StructOpt;
+use thiserror::Error;
+
+/// Start the node and restart it when it exits
+///
+/// Spawned by `jorup run --daemon --restart`, not meant to be run directly.
+#[derive(Debug, StructOpt)]
+pub struct Command {
+    /// The directory of the blockchain
+    #[structopt(long)]
+    dir: PathBuf,
+
+    #[structopt(long)]
+    jcli: PathBuf,
+
+    #[structopt(long)]
+    jormungandr: PathBuf,
+
+    #[structopt(long)]
+    rest_port: Option<u16>,
+
+    #[structopt(long)]
+    keep_logs: usize,
+
+    #[structopt(long)]
+    restart: RestartPolicy,
+
+    #[structopt(long)]
+    max_restarts: u32,
+
+    /// The arguments of the node
+    #[structopt(last = true)]
+    arguments: Vec<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("The supervisor stopped")]
+    Supervise(#[source] runner::Error),
+}
+
+impl Command {
+    pub fn run(self) -> Result<(), Error> {
+        runner::supervise(Supervision {
+            dir: self.dir,
+            jcli: self.jcli,
+            jormungandr: self.jormungandr,
+            arguments: self.arguments,
+            rest_port: self.rest_port,
+            keep_logs: self.keep_logs,
+            restart: Restart {
+                policy: self.restart,
+                max_restarts: self.max_restarts,
+            },
+        })
+        .map_err(Error::Supervise)
+    }
+}
@@ -1,8 +1,7 @@
 use crate::{
     common::JorupConfig,
-    utils::{blockchain::Blockchain, jcli::Jcli, release::Release, version::Version},
+    utils::{blockchain::Blockchain, jcli::Jcli, release::Release, version::VersionReq},
 };
-use std::path::PathBuf;
 use structopt::StructOpt;
 use thiserror::Error;
 
@@ -10,17 +9,17 @@ use thiserror::Error;
 #[derive(Debug, StructOpt)]
 pub struct Command {
     /// The blockchain to run jormungandr for
-    blockchain: String,
+    ///
+    /// Defaults to the `default-blockchain` setting.
+    blockchain: Option<String>,
 
-    /// The version of Jormungandr to run. If not specified, the latest
-    /// compatible version will be used.
-    #[structopt(short, long)]
-    version: Option<Version>,
-
-    /// The directory containing jormungandr and jcli, can be useful for
-    /// development purposes. When provided, the `--version` flag is ignored.
-    #[structopt(long)]
-    bin: Option<PathBuf>,
+    /// The version of Jormungandr to run, or a release channel: `stable`,
+    /// `rc`, `beta`, `latest` or `nightly`, or the name of a custom release
+    /// (see `jorup node build` and `jorup node link`). If not specified, the
+    /// version set with `jorup override` or the latest compatible version
+    /// will be used.
+    #[structopt(short, long, parse(try_from_str = VersionReq::parse_exact))]
+    version: Option<VersionReq>,
 
     /// Force re-creating a wallet if it does exists already
     #[structopt(long)]
@@ -44,28 +43,23 @@ pub enum Error {
 impl Command {
     pub fn run(self, mut cfg: JorupConfig) -> Result<(), Error> {
         // prepare entry directory
-        let blockchain =
-            Blockchain::load(&mut cfg, &self.blockchain).map_err(Error::NoValidBlockchain)?;
+        let blockchain = Blockchain::load_or_default(&mut cfg, self.blockchain.as_deref())
+            .map_err(Error::NoValidBlockchain)?;
         blockchain.prepare().map_err(Error::NoValidBlockchain)?;
 
-        let bin = if let Some(dir) = self.bin {
-            eprintln!("WARN: using custom binaries from {}", dir.display());
-            dir.join("jcli")
+        let release = if let Some(version_req) = self.version {
+            Release::load_matching(&mut cfg, &version_req)
         } else {
-            let release = if let Some(version) = self.version {
-                Release::new(&mut cfg, version)
-            } else {
-                Release::load(&mut cfg, blockchain.jormungandr_version_req())
-            }
-            .map_err(Error::NoCompatibleRelease)?;
+            Release::load(&mut cfg, blockchain.jormungandr_version_req())
+        }
+        .map_err(Error::NoCompatibleRelease)?;
 
-            if release.asset_need_fetched() {
-                // asset release is not available
-                return Err(Error::NoCompatibleBinaries);
-            }
+        if release.asset_need_fetched() {
+            // asset release is not available
+            return Err(Error::NoCompatibleBinaries);
+        }
 
-            release.dir().join("jcli")
-        };
+        let bin = release.get_tool("jcli");
 
         let mut runner = Jcli::new(&blockchain, bin);
 
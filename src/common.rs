@@ -1,14 +1,40 @@
-use crate::utils::download::Client;
-use std::{collections::BTreeSet, io, path::PathBuf};
+use crate::{
+    config::{Config, Registry},
+    settings::{self, Origin, Settings},
+    utils::{
+        download::{self, Client},
+        github::{ReleaseSource, GITHUB_API_URL},
+    },
+};
+use std::{
+    collections::BTreeSet,
+    io,
+    path::{Path, PathBuf},
+};
 use thiserror::Error;
 
+/// Name of the registry maintained in the jorup repository
+pub const OFFICIAL_REGISTRY: &str = "official";
+const OFFICIAL_REGISTRY_URL: &str =
+    "https://raw.githubusercontent.com/input-output-hk/jorup/master/jorfile.json";
+
+const SETTINGS_FILE: &str = "settings.toml";
+const DEFAULT_REST_PORT: u16 = 8080;
+const DEFAULT_P2P_PORT: u16 = 3000;
+const DEFAULT_KEEP_LOGS: usize = 5;
+
 #[derive(Debug)]
 pub struct JorupConfig {
     home_dir: PathBuf,
 
     jor_file: Option<PathBuf>,
-    jor: Option<crate::config::Config>,
-    offline: bool,
+    jor: Option<Config>,
+
+    /// the settings given on the command line, in the environment and in the
+    /// settings file, in order of precedence
+    layers: Vec<(Origin, Settings)>,
+    settings: Settings,
+    release_source: ReleaseSource,
 }
 
 #[derive(Debug, Error)]
@@ -23,17 +49,45 @@ pub enum Error {
     CannotOpenFile(#[source] io::Error, PathBuf),
     #[error("Cannot parse file: {1}")]
     Json(#[source] serde_json::Error, PathBuf),
-    #[error("Cannot sync jorfile with registry")]
-    CannotSyncRegistry(#[source] crate::utils::download::Error),
+    #[error("Cannot write file: {1}")]
+    CannotWriteFile(#[source] io::Error, PathBuf),
+    #[error("Cannot sync jorfile with registry '{1}'")]
+    CannotSyncRegistry(#[source] crate::utils::download::Error, String),
+    #[error("Cannot merge the blockchain registries")]
+    Registry(#[source] crate::config::Error),
+    #[error("Registry '{0}' already exists")]
+    RegistryExists(String),
+    #[error("No registry named '{0}'")]
+    UnknownRegistry(String),
+    #[error("Invalid registry name '{0}', only alphanumeric characters, '-' and '_' are allowed")]
+    InvalidRegistryName(String),
+    #[error("Invalid settings")]
+    Settings(#[source] settings::Error),
 }
 
 impl JorupConfig {
+    /// Create the configuration. The settings given on the command line take
+    /// precedence over the `JORUP_*` environment variables, which take
+    /// precedence over the settings file of the jorup home.
     pub fn new(
         jorup_home: Option<PathBuf>,
         jorfile: Option<PathBuf>,
-        offline: bool,
+        cli_settings: Settings,
+    ) -> Result<Self, Error> {
+        let cfg = Self::new_quiet(jorup_home, jorfile, cli_settings)?;
+        cfg.detect_installed_path();
+        Ok(cfg)
+    }
+
+    /// Same as `new`, without the warnings about the installation. This is
+    /// used by the proxies, which must not add to the output of the tools.
+    pub fn new_quiet(
+        jorup_home: Option<PathBuf>,
+        jorfile: Option<PathBuf>,
+        cli_settings: Settings,
     ) -> Result<Self, Error> {
         let home_dir = jorup_home
+            .or_else(|| std::env::var_os("JORUP_HOME").map(PathBuf::from))
             .or_else(|| dirs::home_dir().map(|d| d.join(".jorup")))
             .ok_or_else(|| Error::NoHomeDir)?;
 
@@ -46,15 +100,30 @@ impl JorupConfig {
         std::fs::create_dir_all(&home_dir)
             .map_err(|e| Error::CannotCreateHomeDir(e, home_dir.clone()))?;
 
+        let env_settings = Settings::from_env().map_err(Error::Settings)?;
+        let file_settings =
+            Settings::load(&home_dir.join(SETTINGS_FILE)).map_err(Error::Settings)?;
+        let layers = vec![
+            (Origin::CommandLine, cli_settings),
+            (Origin::Environment, env_settings),
+            (Origin::File, file_settings),
+        ];
+        let settings = layers
+            .iter()
+            .map(|(_, layer)| layer.clone())
+            .fold(Settings::default(), Settings::or);
+        let release_source = settings.release_source.clone().unwrap_or_default();
+
         let cfg = JorupConfig {
             home_dir,
             jor_file: jorfile,
             jor: None,
-            offline,
+            layers,
+            settings,
+            release_source,
         };
 
         cfg.init()?;
-        cfg.detect_installed_path();
 
         Ok(cfg)
     }
@@ -66,6 +135,8 @@ impl JorupConfig {
             .map_err(|e| Error::CannotCreateInitDir(e, self.blockchain_dir()))?;
         std::fs::create_dir_all(self.release_dir())
             .map_err(|e| Error::CannotCreateInitDir(e, self.release_dir()))?;
+        std::fs::create_dir_all(self.cache_dir())
+            .map_err(|e| Error::CannotCreateInitDir(e, self.cache_dir()))?;
 
         Ok(())
     }
@@ -106,6 +177,10 @@ impl JorupConfig {
             .unwrap_or_else(|| self.home_dir.join("jorfile.json"))
     }
 
+    pub fn home_dir(&self) -> &PathBuf {
+        &self.home_dir
+    }
+
     pub fn bin_dir(&self) -> PathBuf {
         self.home_dir.join("bin")
     }
@@ -118,37 +193,232 @@ impl JorupConfig {
         self.home_dir.join("release")
     }
 
+    pub fn cache_dir(&self) -> PathBuf {
+        self.home_dir.join("cache")
+    }
+
+    pub fn settings_file(&self) -> PathBuf {
+        self.home_dir.join(SETTINGS_FILE)
+    }
+
+    /// The resolved settings
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The value of a setting and where it comes from
+    pub fn setting(&self, key: &str) -> Result<Option<(String, Origin)>, settings::Error> {
+        for (origin, layer) in &self.layers {
+            if let Some(value) = layer.get(key)? {
+                return Ok(Some((value, *origin)));
+            }
+        }
+        Ok(None)
+    }
+
     pub fn offline(&self) -> bool {
-        self.offline
+        self.settings.offline.unwrap_or(false)
+    }
+
+    pub fn default_blockchain(&self) -> Option<&str> {
+        self.settings.default_blockchain.as_deref()
+    }
+
+    pub fn rest_port(&self) -> u16 {
+        self.settings.rest_port.unwrap_or(DEFAULT_REST_PORT)
+    }
+
+    pub fn p2p_port(&self) -> u16 {
+        self.settings.p2p_port.unwrap_or(DEFAULT_P2P_PORT)
+    }
+
+    /// The number of previous logs kept when a node is started again
+    pub fn keep_logs(&self) -> usize {
+        self.settings.keep_logs.unwrap_or(DEFAULT_KEEP_LOGS)
+    }
+
+    pub fn release_source(&self) -> &ReleaseSource {
+        &self.release_source
+    }
+
+    /// Create a download client authenticated with the GitHub token against
+    /// the GitHub API and the GitHub compatible release source, if any.
+    pub fn client(&self) -> Result<Client, download::Error> {
+        let token = &self.settings.github_token;
+        let mut client = Client::new()?
+            .github_auth(GITHUB_API_URL, token.clone())
+            .wait_for_rate_limit(self.settings.wait_for_rate_limit.unwrap_or(false));
+        if let ReleaseSource::GitHub { api, .. } = &self.release_source {
+            client = client.github_auth(api, token.clone());
+        }
+        Ok(client)
+    }
+
+    pub fn default_release_file(&self) -> PathBuf {
+        self.home_dir.join("default_release")
+    }
+
+    pub fn overrides_file(&self) -> PathBuf {
+        self.home_dir.join("overrides.json")
     }
 
-    pub fn sync_jorfile(&self, client: &mut Client) -> Result<(), Error> {
+    fn registries_file(&self) -> PathBuf {
+        self.home_dir.join("registries.json")
+    }
+
+    fn registry_dir(&self) -> PathBuf {
+        self.home_dir.join("registry")
+    }
+
+    /// The local copy of the jorfile of the given registry. The official
+    /// registry is stored in `jorfile.json` like before registries existed.
+    fn registry_jorfile(&self, registry: &Registry) -> PathBuf {
+        if registry.name() == OFFICIAL_REGISTRY {
+            self.jorfile()
+        } else {
+            self.registry_dir()
+                .join(format!("{}.json", registry.name()))
+        }
+    }
+
+    /// The registries configured by the user, in order of precedence. This
+    /// does not include the official registry.
+    pub fn registries(&self) -> Result<Vec<Registry>, Error> {
+        let path = self.registries_file();
+        if !path.is_file() {
+            return Ok(Vec::new());
+        }
+        let file =
+            std::fs::File::open(&path).map_err(|e| Error::CannotOpenFile(e, path.clone()))?;
+        serde_json::from_reader(file).map_err(|e| Error::Json(e, path))
+    }
+
+    fn all_registries(&self) -> Result<Vec<Registry>, Error> {
+        let mut registries = self.registries()?;
+        let official_url = self
+            .settings
+            .registry
+            .clone()
+            .unwrap_or_else(|| OFFICIAL_REGISTRY_URL.to_string());
+        registries.push(Registry::new(OFFICIAL_REGISTRY.to_string(), official_url));
+        Ok(registries)
+    }
+
+    fn save_registries(&self, registries: &[Registry]) -> Result<(), Error> {
+        let path = self.registries_file();
+        std::fs::write(&path, serde_json::to_string_pretty(registries).unwrap())
+            .map_err(|e| Error::CannotWriteFile(e, path))
+    }
+
+    pub fn add_registry(&self, name: String, url: String) -> Result<(), Error> {
+        let valid_name = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid_name {
+            return Err(Error::InvalidRegistryName(name));
+        }
+
+        let mut registries = self.registries()?;
+        if name == OFFICIAL_REGISTRY || registries.iter().any(|r| r.name() == name) {
+            return Err(Error::RegistryExists(name));
+        }
+        registries.push(Registry::new(name, url));
+        self.save_registries(&registries)
+    }
+
+    pub fn remove_registry(&self, name: &str) -> Result<(), Error> {
+        let mut registries = self.registries()?;
+        let registry = match registries.iter().position(|r| r.name() == name) {
+            Some(index) => registries.remove(index),
+            None => return Err(Error::UnknownRegistry(name.to_string())),
+        };
+        self.save_registries(&registries)?;
+
+        let jorfile = self.registry_jorfile(&registry);
+        if jorfile.is_file() {
+            std::fs::remove_file(&jorfile).map_err(|e| Error::CannotWriteFile(e, jorfile))?;
+        }
+        Ok(())
+    }
+
+    pub fn sync_jorfile(&mut self, client: &mut Client) -> Result<(), Error> {
         // do not sync if the jorfile was given as parameter of the
         // command line or if `--offline`
-        if self.jor_file.is_some() || self.offline {
+        if self.jor_file.is_some() || self.offline() {
             return Ok(());
         }
 
-        client
-            .download_file(
-                "jorfile",
-                "https://raw.githubusercontent.com/input-output-hk/jorup/master/jorfile.json",
-                self.jorfile(),
-            )
-            .map_err(Error::CannotSyncRegistry)
+        std::fs::create_dir_all(self.registry_dir())
+            .map_err(|e| Error::CannotCreateInitDir(e, self.registry_dir()))?;
+
+        for registry in self.all_registries()? {
+            client
+                .download_file(
+                    registry.name(),
+                    registry.url(),
+                    self.registry_jorfile(&registry),
+                )
+                .map_err(|e| Error::CannotSyncRegistry(e, registry.name().to_string()))?;
+        }
+
+        // make sure the registries can be used together
+        self.jor = None;
+        self.load_jor().map(|_| ())
     }
 
-    pub fn load_jor(&mut self) -> Result<&crate::config::Config, Error> {
+    /// Load the blockchains from the local copies of the registries.
+    ///
+    /// The registries configured by the user take precedence over the official
+    /// one: a blockchain of the official registry is hidden by a blockchain with
+    /// the same name from a custom registry. Two custom registries must not
+    /// define blockchains with the same name.
+    pub fn load_jor(&mut self) -> Result<&Config, Error> {
         if self.jor.is_none() {
-            let file = std::fs::File::open(self.jorfile()).map_err(|e| {
-                eprintln!("HINT: run `jorup blockchain update`");
-                Error::CannotOpenFile(e, self.jorfile())
-            })?;
-
-            let jor = serde_json::from_reader(file).map_err(|e| Error::Json(e, self.jorfile()))?;
+            let jor = match &self.jor_file {
+                Some(jorfile) => load_jorfile(jorfile, &jorfile.display().to_string())?,
+                None => self.merge_registries()?,
+            };
             self.jor = Some(jor);
         }
 
         Ok(self.jor.as_ref().unwrap())
     }
+
+    fn merge_registries(&self) -> Result<Config, Error> {
+        let mut jor = Config::default();
+        let mut synced = false;
+
+        for registry in self.all_registries()? {
+            let jorfile = self.registry_jorfile(&registry);
+            if !jorfile.is_file() {
+                continue;
+            }
+            synced = true;
+            let config = load_jorfile(&jorfile, registry.name())?;
+            jor.merge(config, registry.name() == OFFICIAL_REGISTRY)
+                .map_err(Error::Registry)?;
+        }
+
+        if !synced {
+            eprintln!("HINT: run `jorup blockchain update`");
+            let jorfile = self.jorfile();
+            return Err(Error::CannotOpenFile(
+                io::Error::from(io::ErrorKind::NotFound),
+                jorfile,
+            ));
+        }
+
+        Ok(jor)
+    }
+}
+
+fn load_jorfile(path: &Path, registry: &str) -> Result<Config, Error> {
+    let file = std::fs::File::open(path).map_err(|e| {
+        eprintln!("HINT: run `jorup blockchain update`");
+        Error::CannotOpenFile(e, path.to_path_buf())
+    })?;
+    let config: Config =
+        serde_json::from_reader(file).map_err(|e| Error::Json(e, path.to_path_buf()))?;
+    config.with_registry(registry).map_err(Error::Registry)
 }
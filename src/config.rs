@@ -1,7 +1,8 @@
 use crate::utils::version::VersionReq;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Config(Vec<Blockchain>);
 
 #[derive(Debug, Clone, Deserialize)]
@@ -11,6 +12,17 @@ pub struct Blockchain {
     jormungandr_versions: VersionReq,
     block0_hash: String,
     trusted_peers: Vec<TrustedPeer>,
+    /// The registry this blockchain was obtained from
+    #[serde(skip)]
+    registry: String,
+}
+
+/// A source of blockchain configurations: an HTTP URL or a local `file://`
+/// path to a jorfile.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Registry {
+    name: String,
+    url: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -19,7 +31,57 @@ pub struct TrustedPeer {
     id: String,
 }
 
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Blockchain '{name}' is defined more than once in registry '{registry}'")]
+    DuplicateBlockchain { name: String, registry: String },
+    #[error("Blockchain '{name}' is defined in both registries '{first}' and '{second}'")]
+    NameCollision {
+        name: String,
+        first: String,
+        second: String,
+    },
+}
+
 impl Config {
+    /// Mark all the blockchains of this configuration as coming from the given
+    /// registry, ensuring the registry does not define a blockchain twice.
+    pub fn with_registry(mut self, registry: &str) -> Result<Self, Error> {
+        for (i, blockchain) in self.0.iter().enumerate() {
+            if self.0[..i].iter().any(|b| b.name == blockchain.name) {
+                return Err(Error::DuplicateBlockchain {
+                    name: blockchain.name.clone(),
+                    registry: registry.to_string(),
+                });
+            }
+        }
+        for blockchain in self.0.iter_mut() {
+            blockchain.registry = registry.to_string();
+        }
+        Ok(self)
+    }
+
+    /// Add the blockchains of a registry with a lower precedence than the
+    /// registries already merged. When `shadowable` is set, the blockchains
+    /// already defined take precedence over the ones of `other` with the same
+    /// name, otherwise a name collision is an error.
+    pub fn merge(&mut self, other: Config, shadowable: bool) -> Result<(), Error> {
+        for blockchain in other.0 {
+            match self.get_blockchain(blockchain.name()) {
+                Some(existing) if !shadowable => {
+                    return Err(Error::NameCollision {
+                        name: blockchain.name,
+                        first: existing.registry.clone(),
+                        second: blockchain.registry,
+                    })
+                }
+                Some(_) => {}
+                None => self.0.push(blockchain),
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_blockchain(&self, name: &str) -> Option<&Blockchain> {
         self.0.iter().find(|blockchain| blockchain.name() == name)
     }
@@ -49,6 +111,24 @@ impl Blockchain {
     pub fn trusted_peers(&self) -> &[TrustedPeer] {
         &self.trusted_peers
     }
+
+    pub fn registry(&self) -> &str {
+        &self.registry
+    }
+}
+
+impl Registry {
+    pub fn new(name: String, url: String) -> Self {
+        Registry { name, url }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
 }
 
 impl TrustedPeer {
@@ -2,22 +2,24 @@ mod commands;
 mod common;
 mod config;
 mod jormungandr_config;
+mod proxy;
+mod settings;
 mod utils;
 
 use commands::Cmd;
-use std::{
-    env::{self, consts::EXE_SUFFIX},
-    error::Error,
-    ffi::OsStr,
-};
+use std::error::Error;
 use structopt::StructOpt;
 
 fn main() {
-    let current_executable = env::current_exe().expect("Failed to get current executable name");
-    let current_executable = current_executable.file_name().unwrap();
-    let init_name = format!("jorup-init{}", EXE_SUFFIX);
-    if current_executable == OsStr::new(&init_name) {
+    // dispatch on the name the program was invoked with rather than on the
+    // executable: the proxies are links to jorup
+    let program = proxy::invoked_program().expect("Failed to get current executable name");
+    if proxy::is_named(&program, "jorup-init") {
         run(commands::Install::from_args())
+    } else if let Some(tool) = proxy::tool(&program) {
+        if let Err(error) = proxy::run(tool) {
+            exit_with(&error)
+        }
     } else {
         run(commands::RootCmd::from_args())
     }
@@ -25,18 +27,22 @@ fn main() {
 
 fn run(app: impl Cmd) {
     if let Err(error) = app.run() {
-        eprintln!("{}", error);
-        let mut source = error.source();
-        while let Some(err) = source {
-            eprintln!(" |-> {}", err);
-            source = err.source();
-        }
+        exit_with(&error)
+    }
+}
 
-        // TODO: https://github.com/rust-lang/rust/issues/43301
-        //
-        // as soon as #43301 is stabilized it would be nice to no use
-        // `exit` but the more appropriate:
-        // https://doc.rust-lang.org/stable/std/process/trait.Termination.html
-        std::process::exit(1);
+fn exit_with(error: &dyn Error) -> ! {
+    eprintln!("{}", error);
+    let mut source = error.source();
+    while let Some(err) = source {
+        eprintln!(" |-> {}", err);
+        source = err.source();
     }
+
+    // TODO: https://github.com/rust-lang/rust/issues/43301
+    //
+    // as soon as #43301 is stabilized it would be nice to no use
+    // `exit` but the more appropriate:
+    // https://doc.rust-lang.org/stable/std/process/trait.Termination.html
+    std::process::exit(1);
 }
@@ -0,0 +1,145 @@
+use crate::{
+    common::JorupConfig,
+    settings::Settings,
+    utils::{
+        release::{self, Release},
+        version::{ReqParseError, VersionReq},
+    },
+};
+use std::{
+    env,
+    ffi::{OsStr, OsString},
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    Common(#[from] crate::common::Error),
+    #[error("Invalid version '+{1}'")]
+    InvalidVersion(#[source] ReqParseError, String),
+    #[error("Cannot find the release to run")]
+    Release(#[from] release::Error),
+    #[error("No default release, run `jorup node install --make-default`")]
+    NoDefault,
+    #[error("Cannot run {1}")]
+    Run(#[source] io::Error, PathBuf),
+}
+
+/// The path jorup was invoked with. The proxies are symbolic links to jorup,
+/// which `current_exe` would resolve. A program found through `PATH` is
+/// invoked with its bare name, it is searched in `PATH` the same way.
+#[cfg(unix)]
+pub fn invoked_program() -> Option<PathBuf> {
+    let program = match env::args_os().next() {
+        Some(program) => PathBuf::from(program),
+        None => return env::current_exe().ok(),
+    };
+    if program.parent() == Some(Path::new("")) {
+        let path = env::var_os("PATH").unwrap_or_default();
+        let found = env::split_paths(&path)
+            .map(|dir| dir.join(&program))
+            .find(|candidate| candidate.is_file());
+        return Some(found.unwrap_or(program));
+    }
+    match env::current_dir() {
+        Ok(dir) if program.is_relative() => Some(dir.join(program)),
+        _ => Some(program),
+    }
+}
+
+/// The path jorup was invoked with. The proxies are hard links or copies of
+/// jorup on Windows, so the executable is the proxy itself, whereas the name
+/// it was invoked with may lack the `.exe` extension.
+#[cfg(windows)]
+pub fn invoked_program() -> Option<PathBuf> {
+    env::current_exe().ok()
+}
+
+/// Whether the program is `name`. File names are case insensitive on Windows,
+/// where the `.exe` extension is ignored.
+pub fn is_named(program: &Path, name: &str) -> bool {
+    if cfg!(windows) {
+        program
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .map(|stem| stem.eq_ignore_ascii_case(name))
+            .unwrap_or(false)
+    } else {
+        program.file_name() == Some(OsStr::new(name))
+    }
+}
+
+/// The tool a proxy stands for, given the program it was invoked as
+pub fn tool(program: &Path) -> Option<&'static str> {
+    release::PROXIES
+        .iter()
+        .find(|tool| is_named(program, tool))
+        .copied()
+}
+
+/// Run the given tool from the release selected by a `+<version>` first
+/// argument, by the override in effect or by the default release.
+pub fn run(tool: &str) -> Result<(), Error> {
+    let mut cfg = JorupConfig::new_quiet(home_dir(), None, Settings::default())?;
+
+    let mut args: Vec<OsString> = env::args_os().skip(1).collect();
+    let version = args
+        .first()
+        .and_then(|arg| arg.to_str())
+        .and_then(|arg| arg.strip_prefix('+'))
+        .map(str::to_string);
+
+    let release = match version {
+        Some(version) => {
+            args.remove(0);
+            let version_req =
+                VersionReq::parse_exact(&version).map_err(|e| Error::InvalidVersion(e, version))?;
+            Release::load_matching(&mut cfg, &version_req)?
+        }
+        None => match Release::load_override(&mut cfg)? {
+            Some(release) => release,
+            None => Release::load_default(&mut cfg)?.ok_or(Error::NoDefault)?,
+        },
+    };
+
+    // the tool, or anything it runs, finds the same jorup home
+    env::set_var("JORUP_HOME", cfg.home_dir());
+    exec(release.get_tool(tool), args)
+}
+
+/// The jorup home of the proxy: `$JORUP_HOME` if set, otherwise the home
+/// containing the `bin` directory the proxy was invoked from, see
+/// `invoked_program`. Without either, the default home is used.
+fn home_dir() -> Option<PathBuf> {
+    if env::var_os("JORUP_HOME").is_some() {
+        return None;
+    }
+    let program = invoked_program()?;
+    let bin_dir = program.parent()?;
+    if bin_dir.file_name() != Some(OsStr::new("bin")) {
+        return None;
+    }
+    bin_dir.parent().map(PathBuf::from)
+}
+
+#[cfg(unix)]
+fn exec(program: PathBuf, args: Vec<OsString>) -> Result<(), Error> {
+    use std::os::unix::process::CommandExt;
+
+    // only returns on failure
+    let error = Command::new(&program).args(args).exec();
+    Err(Error::Run(error, program))
+}
+
+#[cfg(windows)]
+fn exec(program: PathBuf, args: Vec<OsString>) -> Result<(), Error> {
+    let status = Command::new(&program)
+        .args(args)
+        .status()
+        .map_err(|e| Error::Run(e, program))?;
+    std::process::exit(status.code().unwrap_or(1))
+}
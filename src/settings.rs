@@ -0,0 +1,259 @@
+use crate::utils::github::ReleaseSource;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// The keys of the settings, as used in `settings.toml`, by `jorup settings`
+/// and, upper-cased and prefixed with `JORUP_`, as environment variables.
+pub const KEYS: &[&str] = &[
+    "default-blockchain",
+    "rest-port",
+    "p2p-port",
+    "keep-logs",
+    "offline",
+    "registry",
+    "release-source",
+    "github-token",
+    "wait-for-rate-limit",
+];
+
+/// Persistent jorup settings.
+///
+/// The same structure is used for every layer of settings: the command line,
+/// the environment and the settings file. A layer only holds the values it
+/// sets, the layers are then merged with `Settings::or`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Settings {
+    /// The blockchain to use when none is given on the command line
+    pub default_blockchain: Option<String>,
+    /// The default REST API port of the nodes
+    pub rest_port: Option<u16>,
+    /// The default P2P port of the nodes
+    pub p2p_port: Option<u16>,
+    /// The number of previous logs kept for each blockchain
+    pub keep_logs: Option<usize>,
+    pub offline: Option<bool>,
+    /// URL of the official blockchain registry
+    pub registry: Option<String>,
+    pub release_source: Option<ReleaseSource>,
+    pub github_token: Option<String>,
+    pub wait_for_rate_limit: Option<bool>,
+}
+
+/// Where the value of a setting comes from
+#[derive(Debug, Clone, Copy)]
+pub enum Origin {
+    CommandLine,
+    Environment,
+    File,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Unknown setting '{0}', expected one of: {}", KEYS.join(", "))]
+    UnknownKey(String),
+    #[error("Invalid value '{1}' for setting '{0}'")]
+    InvalidValue(
+        String,
+        String,
+        #[source] Box<dyn std::error::Error + Send + Sync>,
+    ),
+    #[error("Invalid value '{1}' for setting '{0}', expected true or false")]
+    InvalidBool(String, String),
+    #[error("Invalid value for the environment variable {0}")]
+    InvalidEnv(String, #[source] Box<Error>),
+    #[error("Cannot read the settings file: {1}")]
+    CannotReadFile(#[source] io::Error, PathBuf),
+    #[error("Cannot parse the settings file: {1}")]
+    CannotParseFile(#[source] toml::de::Error, PathBuf),
+    #[error("Cannot write the settings file: {1}")]
+    CannotWriteFile(#[source] io::Error, PathBuf),
+}
+
+fn parse<T>(key: &str, value: &str) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .map_err(|e| Error::InvalidValue(key.to_string(), value.to_string(), Box::new(e)))
+}
+
+/// Booleans are also commonly written 1/0, yes/no or on/off, in particular in
+/// environment variables.
+fn parse_bool(key: &str, value: &str) -> Result<bool, Error> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(Error::InvalidBool(key.to_string(), value.to_string())),
+    }
+}
+
+impl Settings {
+    /// Load the settings file, a missing file is an empty set of settings.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        if !path.is_file() {
+            return Ok(Settings::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| Error::CannotReadFile(e, path.to_path_buf()))?;
+        toml::from_str(&content).map_err(|e| Error::CannotParseFile(e, path.to_path_buf()))
+    }
+
+    /// Save the settings file. As it may contain a GitHub token, the file is
+    /// only readable by its owner.
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let content = toml::to_string(self).unwrap();
+        std::fs::write(path, content).map_err(|e| Error::CannotWriteFile(e, path.to_path_buf()))?;
+        restrict_permissions(path).map_err(|e| Error::CannotWriteFile(e, path.to_path_buf()))
+    }
+
+    /// Read the `JORUP_*` environment variables. `GITHUB_TOKEN` is also read
+    /// if `JORUP_GITHUB_TOKEN` is not set.
+    pub fn from_env() -> Result<Self, Error> {
+        let mut settings = Settings::default();
+        for key in KEYS {
+            let var = env_var(key);
+            if let Ok(value) = std::env::var(&var) {
+                settings
+                    .set(key, Some(&value))
+                    .map_err(|e| Error::InvalidEnv(var, Box::new(e)))?;
+            }
+        }
+        if settings.github_token.is_none() {
+            settings.github_token = std::env::var("GITHUB_TOKEN").ok();
+        }
+        Ok(settings)
+    }
+
+    pub fn get(&self, key: &str) -> Result<Option<String>, Error> {
+        let value = match key {
+            "default-blockchain" => self.default_blockchain.clone(),
+            "rest-port" => self.rest_port.map(|port| port.to_string()),
+            "p2p-port" => self.p2p_port.map(|port| port.to_string()),
+            "keep-logs" => self.keep_logs.map(|keep| keep.to_string()),
+            "offline" => self.offline.map(|offline| offline.to_string()),
+            "registry" => self.registry.clone(),
+            "release-source" => self.release_source.as_ref().map(ToString::to_string),
+            "github-token" => self.github_token.clone(),
+            "wait-for-rate-limit" => self.wait_for_rate_limit.map(|wait| wait.to_string()),
+            _ => return Err(Error::UnknownKey(key.to_string())),
+        };
+        Ok(value)
+    }
+
+    /// Set or, when `value` is `None`, unset a setting.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<(), Error> {
+        match key {
+            "default-blockchain" => self.default_blockchain = value.map(str::to_string),
+            "rest-port" => self.rest_port = value.map(|v| parse(key, v)).transpose()?,
+            "p2p-port" => self.p2p_port = value.map(|v| parse(key, v)).transpose()?,
+            "keep-logs" => self.keep_logs = value.map(|v| parse(key, v)).transpose()?,
+            "offline" => self.offline = value.map(|v| parse_bool(key, v)).transpose()?,
+            "registry" => self.registry = value.map(str::to_string),
+            "release-source" => self.release_source = value.map(|v| parse(key, v)).transpose()?,
+            "github-token" => self.github_token = value.map(str::to_string),
+            "wait-for-rate-limit" => {
+                self.wait_for_rate_limit = value.map(|v| parse_bool(key, v)).transpose()?
+            }
+            _ => return Err(Error::UnknownKey(key.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Merge two layers of settings, the values of `self` take precedence.
+    pub fn or(self, other: Settings) -> Settings {
+        Settings {
+            default_blockchain: self.default_blockchain.or(other.default_blockchain),
+            rest_port: self.rest_port.or(other.rest_port),
+            p2p_port: self.p2p_port.or(other.p2p_port),
+            keep_logs: self.keep_logs.or(other.keep_logs),
+            offline: self.offline.or(other.offline),
+            registry: self.registry.or(other.registry),
+            release_source: self.release_source.or(other.release_source),
+            github_token: self.github_token.or(other.github_token),
+            wait_for_rate_limit: self.wait_for_rate_limit.or(other.wait_for_rate_limit),
+        }
+    }
+}
+
+/// The environment variable of a setting: `rest-port` is `JORUP_REST_PORT`
+pub fn env_var(key: &str) -> String {
+    format!("JORUP_{}", key.to_uppercase().replace('-', "_"))
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::CommandLine => f.write_str("command line"),
+            Origin::Environment => f.write_str("environment"),
+            Origin::File => f.write_str("settings file"),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(windows)]
+fn restrict_permissions(_: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn or() {
+        let command_line = Settings {
+            rest_port: Some(8443),
+            ..Settings::default()
+        };
+        let environment = Settings {
+            rest_port: Some(9000),
+            offline: Some(true),
+            ..Settings::default()
+        };
+        let file = Settings {
+            offline: Some(false),
+            keep_logs: Some(2),
+            ..Settings::default()
+        };
+
+        let settings = command_line.or(environment).or(file);
+        assert_eq!(settings.rest_port, Some(8443));
+        assert_eq!(settings.offline, Some(true));
+        assert_eq!(settings.keep_logs, Some(2));
+        assert_eq!(settings.p2p_port, None);
+    }
+
+    #[test]
+    fn booleans() {
+        let mut settings = Settings::default();
+        for (value, expected) in &[
+            ("true", true),
+            ("Yes", true),
+            ("ON", true),
+            ("1", true),
+            ("false", false),
+            ("no", false),
+            ("Off", false),
+            ("0", false),
+        ] {
+            settings.set("offline", Some(value)).unwrap();
+            assert_eq!(settings.offline, Some(*expected), "{}", value);
+        }
+        assert!(settings.set("offline", Some("maybe")).is_err());
+    }
+}
@@ -1,10 +1,21 @@
 use crate::{common::JorupConfig, utils::version::VersionReq};
+use chrono::{DateTime, Local};
 use std::{
     io,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
 
+/// The log of the node running in the background
+pub const LOG_FILE: &str = "NODE.logs";
+/// Prefix of the logs of the previous nodes, followed by the time they were
+/// last written
+const ROTATED_LOG_PREFIX: &str = "NODE.logs.";
+/// The description of the node running in the background, see `RunnerInfo`
+pub const RUNNER_FILE: &str = "running_config.json";
+/// The log of the process restarting the node, see `runner::supervise`
+pub const SUPERVISOR_LOG_FILE: &str = "SUPERVISOR.logs";
+
 pub struct Blockchain {
     entry: crate::config::Blockchain,
 
@@ -17,6 +28,8 @@ pub enum Error {
     NoJorfile(#[source] crate::common::Error),
     #[error("No entry available for the given version")]
     NoEntry,
+    #[error("No blockchain given and no `default-blockchain` setting")]
+    NoDefaultBlockchain,
     #[error("Cannot create directory: {1}")]
     CannotCreateDirectory(#[source] io::Error, PathBuf),
     #[error("Cannot write to file: {1}")]
@@ -36,6 +49,19 @@ impl Blockchain {
         }
     }
 
+    /// Load the given blockchain, or the `default-blockchain` setting if no
+    /// blockchain is given.
+    pub fn load_or_default(
+        cfg: &mut JorupConfig,
+        blockchain_name: Option<&str>,
+    ) -> Result<Self, Error> {
+        let name = blockchain_name
+            .or_else(|| cfg.default_blockchain())
+            .map(str::to_string)
+            .ok_or(Error::NoDefaultBlockchain)?;
+        Self::load(cfg, &name)
+    }
+
     fn new(cfg: &JorupConfig, entry: crate::config::Blockchain) -> Result<Self, Error> {
         let path = cfg.blockchain_dir().join(entry.name().to_string());
         std::fs::create_dir_all(&path)
@@ -63,11 +89,15 @@ impl Blockchain {
     }
 
     pub fn get_log_file(&self) -> PathBuf {
-        self.dir().join("NODE.logs")
+        self.dir().join(LOG_FILE)
     }
 
     pub fn get_runner_file(&self) -> PathBuf {
-        self.dir().join("running_config.json")
+        self.dir().join(RUNNER_FILE)
+    }
+
+    pub fn get_supervisor_log_file(&self) -> PathBuf {
+        self.dir().join(SUPERVISOR_LOG_FILE)
     }
 
     pub fn get_genesis_block_hash(&self) -> PathBuf {
@@ -102,3 +132,77 @@ where
 
     std::fs::write(path, content)
 }
+
+/// The logs of the previous nodes in a blockchain directory, oldest first
+fn rotated_log_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.starts_with(ROTATED_LOG_PREFIX))
+                .unwrap_or(false)
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Move the log of the previous node of a blockchain directory to a file
+/// named after the time it was last written, keeping the `keep` most recent
+/// of these files.
+pub fn rotate_logs(dir: &Path, keep: usize) -> io::Result<()> {
+    let log_file = dir.join(LOG_FILE);
+    if let Ok(metadata) = log_file.metadata() {
+        if metadata.len() > 0 {
+            let modified: DateTime<Local> = metadata
+                .modified()
+                .map(DateTime::from)
+                .unwrap_or_else(|_| Local::now());
+            let name = format!(
+                "{}{}",
+                ROTATED_LOG_PREFIX,
+                modified.format("%Y%m%d-%H%M%S%.9f")
+            );
+            // the modification time may be coarse on some file systems, a
+            // previous log must not be overwritten
+            let mut rotated = dir.join(&name);
+            let mut counter = 0;
+            while rotated.exists() {
+                counter += 1;
+                rotated = dir.join(format!("{}-{}", name, counter));
+            }
+            std::fs::rename(&log_file, rotated)?;
+        }
+    }
+
+    let rotated = rotated_log_files(dir)?;
+    for path in rotated.iter().take(rotated.len().saturating_sub(keep)) {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// The runner files of every blockchain directory, with the name of the
+/// blockchain, sorted by name. The blockchains do not need to be known by the
+/// jorfile anymore.
+pub fn runner_files(cfg: &JorupConfig) -> io::Result<Vec<(String, PathBuf)>> {
+    let blockchain_dir = cfg.blockchain_dir();
+    if !blockchain_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut runner_files: Vec<_> = std::fs::read_dir(&blockchain_dir)?
+        .filter_map(Result::ok)
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().into_owned(),
+                entry.path().join(RUNNER_FILE),
+            )
+        })
+        .filter(|(_, runner_file)| runner_file.is_file())
+        .collect();
+    runner_files.sort();
+    Ok(runner_files)
+}
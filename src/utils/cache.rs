@@ -0,0 +1,146 @@
+use crate::{
+    common::JorupConfig,
+    utils::download::{self, Client},
+};
+use chrono::{offset::Utc, DateTime, Duration};
+use serde::{Deserialize, Serialize};
+use std::{fs, io, path::PathBuf};
+use thiserror::Error;
+
+/// For how long a cached response is served without asking the server
+const CACHE_TTL_MINUTES: i64 = 10;
+
+/// Local cache of the release metadata queried from the release servers.
+///
+/// Entries are revalidated with `If-None-Match` once they are older than the
+/// cache TTL. In offline mode the cached entries are served regardless of
+/// their age.
+pub struct MetadataCache {
+    dir: PathBuf,
+    offline: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    url: String,
+    etag: Option<String>,
+    fetched_at: DateTime<Utc>,
+    body: String,
+    #[serde(default)]
+    next: Option<String>,
+}
+
+/// A page of a paginated resource
+pub struct Page {
+    pub body: String,
+    pub next: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("No cached data for {0}, cannot fetch it offline")]
+    NotCached(String),
+    #[error("Cannot fetch {1}")]
+    Download(#[source] download::Error, String),
+    #[error("The data received from {0} is not valid UTF-8")]
+    InvalidData(String),
+    #[error("Cannot write the cache file: {1}")]
+    CannotWriteFile(#[source] io::Error, PathBuf),
+}
+
+impl MetadataCache {
+    pub fn new(cfg: &JorupConfig) -> Self {
+        MetadataCache {
+            dir: cfg.cache_dir(),
+            offline: cfg.offline(),
+        }
+    }
+
+    /// Get the content behind the given URL, from the cache if possible.
+    pub fn fetch(&self, client: &mut Client, what: &str, url: &str) -> Result<String, Error> {
+        self.fetch_page(client, what, url).map(|page| page.body)
+    }
+
+    /// Get a page of a paginated resource, along with the URL of the next
+    /// page, from the cache if possible.
+    pub fn fetch_page(&self, client: &mut Client, what: &str, url: &str) -> Result<Page, Error> {
+        let entry = self.load(url);
+
+        if self.offline {
+            return entry
+                .map(Entry::into_page)
+                .ok_or_else(|| Error::NotCached(url.to_string()));
+        }
+
+        let entry = match entry {
+            Some(entry) if Utc::now() - entry.fetched_at < Duration::minutes(CACHE_TTL_MINUTES) => {
+                return Ok(entry.into_page())
+            }
+            entry => entry,
+        };
+
+        let etag = entry.as_ref().and_then(|entry| entry.etag.as_deref());
+        let fetched = match client.download_if_modified(what, url, etag) {
+            Ok(fetched) => fetched,
+            Err(err) => match entry {
+                Some(entry) => {
+                    eprintln!("WARN: cannot refresh {}, using cached data", url);
+                    return Ok(entry.into_page());
+                }
+                None => return Err(Error::Download(err, url.to_string())),
+            },
+        };
+
+        let entry = match (fetched, entry) {
+            (Some(modified), _) => Entry {
+                url: url.to_string(),
+                etag: modified.etag,
+                fetched_at: Utc::now(),
+                body: String::from_utf8(modified.body)
+                    .map_err(|_| Error::InvalidData(url.to_string()))?,
+                next: modified.next,
+            },
+            (None, Some(entry)) => Entry {
+                fetched_at: Utc::now(),
+                ..entry
+            },
+            // we did not send an entity tag, the server cannot answer with
+            // "not modified"
+            (None, None) => return Err(Error::NotCached(url.to_string())),
+        };
+
+        self.store(&entry)?;
+
+        Ok(entry.into_page())
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        let name: String = url
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.dir.join(format!("{}.json", name))
+    }
+
+    fn load(&self, url: &str) -> Option<Entry> {
+        let content = fs::read_to_string(self.entry_path(url)).ok()?;
+        serde_json::from_str::<Entry>(&content)
+            .ok()
+            .filter(|entry| entry.url == url)
+    }
+
+    fn store(&self, entry: &Entry) -> Result<(), Error> {
+        let path = self.entry_path(&entry.url);
+        fs::write(&path, serde_json::to_string(entry).unwrap())
+            .map_err(|e| Error::CannotWriteFile(e, path))
+    }
+}
+
+impl Entry {
+    fn into_page(self) -> Page {
+        Page {
+            body: self.body,
+            next: self.next,
+        }
+    }
+}
@@ -1,6 +1,16 @@
+use chrono::{offset::Utc, DateTime, TimeZone};
 use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::{
+    blocking::{RequestBuilder, Response},
+    header::{
+        AUTHORIZATION, CONTENT_RANGE, ETAG, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, LINK, RANGE,
+    },
+    StatusCode,
+};
+use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use thiserror::Error;
 
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
@@ -24,10 +34,34 @@ pub enum Error {
     NewClient(#[source] reqwest::Error),
     #[error("Cannot write to the provided destination")]
     DownloadToWriter(#[source] reqwest::Error),
+    #[error("Download of '{asset}' is incomplete: received {received} bytes out of {expected}")]
+    IncompleteDownload {
+        asset: String,
+        received: u64,
+        expected: u64,
+    },
+    #[error("Cannot move the downloaded file into place: {1}")]
+    CannotFinalizeDownload(#[source] io::Error, PathBuf),
+    #[error("Cannot copy the local file {1}")]
+    CannotCopyFile(#[source] io::Error, PathBuf),
+    #[error("API rate limit exceeded, the limit resets at {reset}")]
+    RateLimited { reset: DateTime<Utc> },
 }
 
 pub struct Client {
     inner: reqwest::blocking::Client,
+    /// Base URLs of the GitHub APIs the token is sent to
+    github_apis: Vec<String>,
+    github_token: Option<String>,
+    wait_for_rate_limit: bool,
+}
+
+/// The content of a resource downloaded with `Client::download_if_modified`
+pub struct Modified {
+    pub body: Vec<u8>,
+    pub etag: Option<String>,
+    /// The next page of a paginated resource, from the `Link` header
+    pub next: Option<String>,
 }
 
 impl Client {
@@ -37,65 +71,257 @@ impl Client {
             .user_agent(APP_USER_AGENT)
             .build()
             .map_err(Error::NewClient)?;
-        Ok(Self { inner: client })
+        Ok(Self {
+            inner: client,
+            github_apis: Vec::new(),
+            github_token: None,
+            wait_for_rate_limit: false,
+        })
     }
 
-    pub fn download_to_writer<W: io::Write>(
+    /// Authenticate the requests to the given GitHub API with the token, if
+    /// any. The token is never sent to other hosts.
+    pub fn github_auth(mut self, api: &str, token: Option<String>) -> Self {
+        self.github_apis.push(api.trim_end_matches('/').to_string());
+        self.github_token = token;
+        self
+    }
+
+    /// When an API rate limit is exceeded, wait for it to reset and retry
+    /// instead of failing.
+    pub fn wait_for_rate_limit(mut self, wait: bool) -> Self {
+        self.wait_for_rate_limit = wait;
+        self
+    }
+
+    fn request(&self, url: &str) -> RequestBuilder {
+        let request = self.inner.get(url);
+        let is_github_api = self
+            .github_apis
+            .iter()
+            .any(|api| url.starts_with(&format!("{}/", api)));
+        match &self.github_token {
+            Some(token) if is_github_api => {
+                request.header(AUTHORIZATION, format!("token {}", token))
+            }
+            _ => request,
+        }
+    }
+
+    /// Download a resource unless it still matches the given entity tag.
+    /// Returns `None` if the server reports the resource as not modified.
+    pub fn download_if_modified(
         &mut self,
         what: &str,
         url: &str,
-        to: &mut W,
-    ) -> Result<(), Error> {
-        self.download_internal(what, url, to)
-            .map_err(Error::DownloadToWriter)
+        etag: Option<&str>,
+    ) -> Result<Option<Modified>, Error> {
+        let response = loop {
+            let mut request = self.request(url);
+            if let Some(etag) = etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            let response = request.send().map_err(Error::DownloadToWriter)?;
+
+            let reset = match rate_limit_reset(&response) {
+                Some(reset) => reset,
+                None => break response,
+            };
+            if !self.wait_for_rate_limit {
+                return Err(Error::RateLimited { reset });
+            }
+            eprintln!(
+                "WARN: API rate limit exceeded, waiting until {} to retry",
+                reset
+            );
+            let wait = (reset - Utc::now()).to_std().unwrap_or_default();
+            std::thread::sleep(wait + Duration::from_secs(1));
+        };
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok(None);
+        }
+
+        let response = response
+            .error_for_status()
+            .map_err(Error::DownloadToWriter)?;
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let next = response
+            .headers()
+            .get(LINK)
+            .and_then(|link| link.to_str().ok())
+            .and_then(next_page_link);
+        let mut body = Vec::new();
+        Self::copy_response(what, response, 0, &mut body).map_err(Error::DownloadToWriter)?;
+
+        Ok(Some(Modified { body, etag, next }))
     }
 
+    /// Download a file into the given destination.
+    ///
+    /// The data is written to a `.part` file next to the destination first,
+    /// and moved into place only once the transfer is complete. If a `.part`
+    /// file is left over from an interrupted download, the transfer is resumed
+    /// with an HTTP Range request. The entity tag or the modification date of
+    /// the resource is kept next to the `.part` file and sent with the Range
+    /// request, so that the server sends the whole resource again if it
+    /// changed in the meantime. Without either, the transfer starts from
+    /// scratch. `file://` URLs are copied from the local file system.
     pub fn download_file<P: AsRef<Path>>(
         &mut self,
         what: &str,
         url: &str,
         to: P,
     ) -> Result<(), Error> {
-        let mut file = std::fs::OpenOptions::new()
+        let to = to.as_ref();
+        let part = part_file(to);
+
+        if let Some(path) = url.strip_prefix("file://") {
+            fs::copy(path, &part).map_err(|e| Error::CannotCopyFile(e, PathBuf::from(path)))?;
+            return fs::rename(&part, to)
+                .map_err(|e| Error::CannotFinalizeDownload(e, to.to_path_buf()));
+        }
+
+        let validator_file = validator_file(to);
+        let validator = fs::read_to_string(&validator_file)
+            .ok()
+            .filter(|validator| !validator.is_empty());
+        let offset = match &validator {
+            Some(_) => fs::metadata(&part).map(|m| m.len()).unwrap_or(0),
+            None => 0,
+        };
+        let mut response = self.get(url, offset, validator.as_deref()).map_err(|e| {
+            Error::CannotDownloadAsset {
+                source: e,
+                asset: what.to_owned(),
+                destination: to.to_path_buf(),
+            }
+        })?;
+
+        // the server either ignored the range or cannot satisfy it (the file
+        // changed upstream for example): start from scratch
+        let offset = if offset > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+                response = self
+                    .get(url, 0, None)
+                    .map_err(|e| Error::CannotDownloadAsset {
+                        source: e,
+                        asset: what.to_owned(),
+                        destination: to.to_path_buf(),
+                    })?;
+            }
+            0
+        } else {
+            offset
+        };
+
+        let response = response
+            .error_for_status()
+            .map_err(|e| Error::CannotDownloadAsset {
+                source: e,
+                asset: what.to_owned(),
+                destination: to.to_path_buf(),
+            })?;
+
+        if offset == 0 {
+            match response_validator(&response) {
+                Some(validator) => fs::write(&validator_file, validator),
+                None => fs::remove_file(&validator_file).or_else(ignore_not_found),
+            }
+            .map_err(|e| Error::CannotCreateDestinationFile(e, validator_file.clone()))?;
+        }
+
+        let expected = if offset > 0 {
+            response
+                .headers()
+                .get(CONTENT_RANGE)
+                .and_then(|range| range.to_str().ok())
+                .and_then(content_range_total)
+        } else {
+            response.content_length()
+        };
+
+        let mut file = fs::OpenOptions::new()
             .create(true)
             .write(true)
-            .open(to.as_ref())
-            .map_err(|e| Error::CannotCreateDestinationFile(e, to.as_ref().to_path_buf()))?;
+            .append(offset > 0)
+            .truncate(offset == 0)
+            .open(&part)
+            .map_err(|e| Error::CannotCreateDestinationFile(e, part.clone()))?;
 
-        self.download_internal(what, url, &mut file)
-            .map_err(|e| Error::CannotDownloadAsset {
+        let received = Self::copy_response(what, response, offset, &mut file).map_err(|e| {
+            Error::CannotDownloadAsset {
                 source: e,
                 asset: what.to_owned(),
-                destination: to.as_ref().to_path_buf(),
-            })
+                destination: to.to_path_buf(),
+            }
+        })?;
+
+        if let Some(expected) = expected {
+            if received != expected {
+                return Err(Error::IncompleteDownload {
+                    asset: what.to_owned(),
+                    received,
+                    expected,
+                });
+            }
+        }
+
+        drop(file);
+        fs::rename(&part, to).map_err(|e| Error::CannotFinalizeDownload(e, to.to_path_buf()))?;
+        fs::remove_file(&validator_file)
+            .or_else(ignore_not_found)
+            .map_err(|e| Error::CannotFinalizeDownload(e, validator_file))
     }
 
-    fn download_internal<W: io::Write>(
+    /// Request the resource from `offset`, only if it still matches the
+    /// validator of the previous transfer
+    fn get(
         &mut self,
-        what: &str,
         url: &str,
+        offset: u64,
+        validator: Option<&str>,
+    ) -> Result<Response, reqwest::Error> {
+        let mut request = self.request(url);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
+            if let Some(validator) = validator {
+                request = request.header(IF_RANGE, validator);
+            }
+        }
+        self.inner.execute(request.build()?)
+    }
+
+    /// Copy the body of the response into the writer while displaying the
+    /// progress. Returns the total number of bytes of the resource received
+    /// so far, including the `offset` bytes received by a previous transfer.
+    fn copy_response<W: io::Write>(
+        what: &str,
+        mut response: Response,
+        offset: u64,
         to: &mut W,
-    ) -> std::result::Result<(), reqwest::Error> {
+    ) -> std::result::Result<u64, reqwest::Error> {
         let style = ProgressStyle::default_bar().template(INDICATIF_TEMPLATE);
         let progress = ProgressBar::new(INDICATIF_LENGTH).with_style(style);
         progress.set_message(what);
 
-        let mut response = self
-            .inner
-            .execute(self.inner.get(url).build()?)?
-            .error_for_status()?;
         let res = if let Some(total) = response.content_length() {
-            progress.set_length(total);
+            progress.set_length(offset + total);
+            progress.set_position(offset);
             let mut writer = WriterWithProgress {
                 inner: to,
                 progress: &progress,
-                written: 0,
+                written: offset,
             };
             response.copy_to(&mut writer)
         } else {
             response.copy_to(to)
         }
-        .map(|_| ());
+        .map(|copied| offset + copied);
 
         if res.is_err() {
             progress.finish_at_current_pos();
@@ -107,6 +333,85 @@ impl Client {
     }
 }
 
+/// If the response reports an exhausted API rate limit (as GitHub does with
+/// the `X-RateLimit-*` headers), returns when the limit resets.
+fn rate_limit_reset(response: &Response) -> Option<DateTime<Utc>> {
+    if response.status() != StatusCode::FORBIDDEN
+        && response.status() != StatusCode::TOO_MANY_REQUESTS
+    {
+        return None;
+    }
+
+    let header =
+        |name: &str| -> Option<i64> { response.headers().get(name)?.to_str().ok()?.parse().ok() };
+    if header("x-ratelimit-remaining")? != 0 {
+        return None;
+    }
+    let reset = header("x-ratelimit-reset")?;
+    Some(Utc.timestamp(reset, 0))
+}
+
+/// Find the `rel="next"` URL of a `Link` header, for example:
+/// `<https://api.github.com/repositories/1/releases?page=2>; rel="next", <...>; rel="last"`
+fn next_page_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let url = parts.next()?.trim();
+        let is_next = parts.any(|param| param.trim() == r#"rel="next""#);
+        if is_next && url.starts_with('<') && url.ends_with('>') {
+            Some(url[1..url.len() - 1].to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// Whether an interrupted download into the given destination is left over
+/// and would be resumed by `Client::download_file`.
+pub fn can_resume(to: &Path) -> bool {
+    part_file(to).is_file()
+        && fs::metadata(validator_file(to))
+            .map(|metadata| metadata.len() > 0)
+            .unwrap_or(false)
+}
+
+fn part_file(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// The validator of the resource being downloaded into the `.part` file
+fn validator_file(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".part.validator");
+    PathBuf::from(name)
+}
+
+/// The validator to resume the download with: `If-Range` only accepts a
+/// strong entity tag, otherwise the modification date.
+fn response_validator(response: &Response) -> Option<String> {
+    let header = |name| response.headers().get(name)?.to_str().ok();
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+        .map(str::to_string)
+}
+
+fn ignore_not_found(error: io::Error) -> io::Result<()> {
+    if error.kind() == io::ErrorKind::NotFound {
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+/// Extract the complete length of the resource from a `Content-Range: bytes
+/// start-end/total` header.
+fn content_range_total(content_range: &str) -> Option<u64> {
+    content_range.rsplit('/').next()?.parse().ok()
+}
+
 struct WriterWithProgress<'a, W> {
     inner: W,
     progress: &'a ProgressBar,
@@ -128,3 +433,33 @@ where
         self.inner.flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_page() {
+        let link = r#"<https://api.github.com/repositories/1/releases?page=2>; rel="next", <https://api.github.com/repositories/1/releases?page=5>; rel="last""#;
+        assert_eq!(
+            next_page_link(link).as_deref(),
+            Some("https://api.github.com/repositories/1/releases?page=2")
+        );
+    }
+
+    #[test]
+    fn last_page() {
+        let link = r#"<https://api.github.com/repositories/1/releases?page=4>; rel="prev", <https://api.github.com/repositories/1/releases?page=1>; rel="first""#;
+        assert_eq!(next_page_link(link), None);
+        assert_eq!(next_page_link(""), None);
+        assert_eq!(next_page_link(r#"https://example.com; rel="next""#), None);
+    }
+
+    #[test]
+    fn content_range() {
+        assert_eq!(content_range_total("bytes 100-199/1000"), Some(1000));
+        assert_eq!(content_range_total("bytes 0-0/1"), Some(1));
+        assert_eq!(content_range_total("bytes 100-199/*"), None);
+        assert_eq!(content_range_total("bytes */1000"), Some(1000));
+    }
+}
@@ -1,9 +1,40 @@
-use super::download::{self, Client};
-use crate::utils::version::{SemVerError, Version, VersionReq};
+use super::{
+    cache::{self, MetadataCache},
+    download::{self, Client},
+};
+use crate::utils::version::{Channel, SemVerError, Version, VersionReq};
 use chrono::{offset::Utc, DateTime};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::{convert::TryFrom, fmt, fs, io, path::PathBuf, str::FromStr};
 use thiserror::Error;
 
+pub const GITHUB_API_URL: &str = "https://api.github.com";
+const JORUP_RELEASES_URL: &str = "https://api.github.com/repos/input-output-hk/jorup/releases";
+const CHECKSUM_SUFFIX: &str = ".sha256";
+const MIRROR_INDEX: &str = "index.json";
+const RELEASES_PER_PAGE: u32 = 100;
+
+/// Where the jormungandr releases are obtained from.
+///
+/// The source is given as a string:
+///
+/// * `github:<owner>/<repo>` for a repository on GitHub;
+/// * `github:<api-url>/<owner>/<repo>` for a GitHub compatible API, for
+///   example a GitHub Enterprise instance;
+/// * a local directory (path or `file://` URL) or an HTTP URL for a mirror
+///   with a `<version>/<asset>` layout. An HTTP mirror must also serve an
+///   `index.json` file listing its releases, see `MirrorReleaseDef`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum ReleaseSource {
+    GitHub {
+        api: String,
+        owner: String,
+        repo: String,
+    },
+    Mirror(String),
+}
+
 pub struct Release {
     version: Version,
     assets: Vec<AssetDef>,
@@ -26,55 +57,106 @@ struct AssetDef {
     name: String,
 }
 
+/// An entry of the `index.json` file of an HTTP mirror, for example:
+///
+/// ```json
+/// [{ "tag_name": "v0.8.19", "assets": ["jormungandr-v0.8.19-x86_64-unknown-linux-gnu-generic.tar.gz"] }]
+/// ```
+///
+/// `published_at` is needed only for the nightly release.
+#[derive(Deserialize)]
+struct MirrorReleaseDef {
+    tag_name: String,
+    assets: Vec<String>,
+    published_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Failed to fetch releases")]
-    CannotGetReleaseData(#[from] download::Error),
+    CannotGetReleaseData(#[source] cache::Error),
+    #[error("GitHub API rate limit exceeded, the limit resets at {reset}. Set GITHUB_TOKEN to get a higher limit or use `--wait-for-rate-limit`")]
+    RateLimited { reset: DateTime<Utc> },
     #[error("Cannot parse the release data")]
     MalformedReleaseData(#[from] serde_json::Error),
     #[error("No release matching {0}")]
     ReleaseNotFound(VersionReq),
+    #[error("Cannot parse the release version")]
+    MalformedVersion(#[from] SemVerError),
+    #[error("Cannot read the release mirror directory: {1}")]
+    MirrorDirectory(#[source] io::Error, PathBuf),
 }
 
-fn download_release_by_url(client: &mut Client, url: &str) -> Result<ReleaseDef, Error> {
-    let mut release_data_raw: Vec<u8> = Vec::new();
-    client.download_to_writer("GitHub release", &url, &mut release_data_raw)?;
-    serde_json::from_slice(&release_data_raw).map_err(Into::into)
+#[derive(Debug, Error)]
+#[error("Invalid release source '{0}', expected `github:<owner>/<repo>`, `github:<api-url>/<owner>/<repo>`, a directory or an URL")]
+pub struct ReleaseSourceError(String);
+
+impl From<cache::Error> for Error {
+    fn from(error: cache::Error) -> Self {
+        match error {
+            cache::Error::Download(download::Error::RateLimited { reset }, _) => {
+                Error::RateLimited { reset }
+            }
+            error => Error::CannotGetReleaseData(error),
+        }
+    }
 }
 
-fn get_exact_release(client: &mut Client, version: VersionReq) -> Result<Release, Error> {
-    let version = version.into_version().unwrap();
-    let url = format!(
-        "https://api.github.com/repos/input-output-hk/jormungandr/releases/tags/{}",
-        version.to_git_tag(),
-    );
-    let release_def = download_release_by_url(client, &url)?;
+fn download_release_by_url(
+    client: &mut Client,
+    cache: &MetadataCache,
+    url: &str,
+) -> Result<ReleaseDef, Error> {
+    let release_data_raw = cache.fetch(client, "GitHub release", url)?;
+    serde_json::from_str(&release_data_raw).map_err(Into::into)
+}
+
+fn get_exact_release(
+    client: &mut Client,
+    cache: &MetadataCache,
+    releases_url: &str,
+    version_req: VersionReq,
+) -> Result<Release, Error> {
+    let version = version_req.clone().into_version().unwrap();
+    let url = format!("{}/tags/{}", releases_url, version.to_git_tag());
+    let release_def = match download_release_by_url(client, cache, &url) {
+        Ok(release_def) => release_def,
+        // this particular release was never queried, but it may be part of
+        // the cached list of releases
+        Err(Error::CannotGetReleaseData(cache::Error::NotCached(_))) => {
+            return find_release_by_req(client, cache, releases_url, &version_req)
+        }
+        Err(err) => return Err(err),
+    };
     Ok(Release {
         version,
         assets: release_def.assets,
     })
 }
 
-fn get_latest_release(client: &mut Client) -> Result<Release, Error> {
-    let release_def = download_release_by_url(
-        client,
-        "https://api.github.com/repos/input-output-hk/jormungandr/releases/latest",
-    )?;
-    let version = Version::from_git_tag(&release_def.tag_name).unwrap();
+fn get_latest_release(
+    client: &mut Client,
+    cache: &MetadataCache,
+    releases_url: &str,
+) -> Result<Release, Error> {
+    let url = format!("{}/latest", releases_url);
+    let release_def = download_release_by_url(client, cache, &url)?;
+    let version = Version::from_git_tag(&release_def.tag_name)?;
     Ok(Release {
         version,
         assets: release_def.assets,
     })
 }
 
-fn get_nightly_release(client: &mut Client) -> Result<Release, Error> {
-    let latest = get_latest_release(client)?;
-    let release_def = download_release_by_url(
-        client,
-        "https://api.github.com/repos/input-output-hk/jormungandr/releases/tags/nightly",
-    )?;
-    let version = Version::from_git_tag(&release_def.tag_name)
-        .unwrap()
+fn get_nightly_release(
+    client: &mut Client,
+    cache: &MetadataCache,
+    releases_url: &str,
+) -> Result<Release, Error> {
+    let latest = get_latest_release(client, cache, releases_url)?;
+    let url = format!("{}/tags/nightly", releases_url);
+    let release_def = download_release_by_url(client, cache, &url)?;
+    let version = Version::from_git_tag(&release_def.tag_name)?
         .configure_nightly(latest.version, release_def.published_at);
     Ok(Release {
         version,
@@ -82,57 +164,372 @@ fn get_nightly_release(client: &mut Client) -> Result<Release, Error> {
     })
 }
 
-fn find_release_by_req(client: &mut Client, version_req: &VersionReq) -> Result<Release, Error> {
-    let mut releases_data_raw: Vec<u8> = Vec::new();
-    client.download_to_writer(
-        "GitHub releases",
-        "https://api.github.com/repos/input-output-hk/jormungandr/releases",
-        &mut releases_data_raw,
-    )?;
+/// Search the releases for the highest version matching the requirement.
+///
+/// The releases are listed newest first, one page at a time. The search stops
+/// at the first page with a matching release, and the highest matching version
+/// of this page is returned.
+fn find_release_by_req(
+    client: &mut Client,
+    cache: &MetadataCache,
+    releases_url: &str,
+    version_req: &VersionReq,
+) -> Result<Release, Error> {
+    let mut next = Some(format!("{}?per_page={}", releases_url, RELEASES_PER_PAGE));
 
-    let releases: ReleasesDef = serde_json::from_slice(&releases_data_raw)?;
+    while let Some(url) = next {
+        let page = cache.fetch_page(client, "GitHub releases", &url)?;
+        let releases: ReleasesDef = serde_json::from_str(&page.body)?;
 
-    let release = releases
-        .0
-        .into_iter()
-        .map(|release_def| {
-            Ok::<_, SemVerError>(Release {
-                version: Version::from_git_tag(&release_def.tag_name)?,
-                assets: release_def.assets,
+        let matching = releases
+            .0
+            .into_iter()
+            .map(|release_def| {
+                Ok::<_, SemVerError>(Release {
+                    version: Version::from_git_tag(&release_def.tag_name)?,
+                    assets: release_def.assets,
+                })
             })
-        })
-        .filter_map(core::result::Result::ok)
-        .find(|release| version_req.matches(&release.version));
+            .filter_map(core::result::Result::ok)
+            .filter(|release| version_req.matches(&release.version));
+
+        if let Some(release) = matching.max_by(|a, b| a.version.cmp(&b.version)) {
+            return Ok(release);
+        }
+        next = page.next;
+    }
 
-    match release {
-        Some(release) => Ok(release),
-        None => Err(Error::ReleaseNotFound(version_req.clone())),
+    Err(Error::ReleaseNotFound(version_req.clone()))
+}
+
+/// The local directory of a mirror, if it is not served over HTTP
+fn mirror_local_dir(base: &str) -> Option<PathBuf> {
+    match base.strip_prefix("file://") {
+        Some(path) => Some(PathBuf::from(path)),
+        None if !base.contains("://") => Some(PathBuf::from(base)),
+        None => None,
     }
 }
 
-pub fn find_matching_release(
+fn list_mirror_releases(
     client: &mut Client,
-    version_req: VersionReq,
+    cache: &MetadataCache,
+    base: &str,
+) -> Result<Vec<ReleaseDef>, Error> {
+    let dir = match mirror_local_dir(base) {
+        Some(dir) => dir,
+        None => {
+            let base = base.trim_end_matches('/');
+            let url = format!("{}/{}", base, MIRROR_INDEX);
+            let index = cache.fetch(client, "mirror releases", &url)?;
+            let index: Vec<MirrorReleaseDef> = serde_json::from_str(&index)?;
+            return Ok(index
+                .into_iter()
+                .map(|release| {
+                    let tag_name = release.tag_name;
+                    ReleaseDef {
+                        assets: release
+                            .assets
+                            .into_iter()
+                            .map(|name| AssetDef {
+                                url: format!("{}/{}/{}", base, tag_name, name),
+                                name,
+                            })
+                            .collect(),
+                        published_at: release.published_at.unwrap_or_else(Utc::now),
+                        tag_name,
+                    }
+                })
+                .collect());
+        }
+    };
+
+    let mut releases = Vec::new();
+    let entries = fs::read_dir(&dir).map_err(|e| Error::MirrorDirectory(e, dir.clone()))?;
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let tag_name = entry.file_name().to_string_lossy().into_owned();
+        let published_at = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        let assets = fs::read_dir(&path)
+            .map_err(|e| Error::MirrorDirectory(e, path.clone()))?
+            .filter_map(Result::ok)
+            .filter(|asset| asset.path().is_file())
+            .map(|asset| AssetDef {
+                url: format!("file://{}", asset.path().display()),
+                name: asset.file_name().to_string_lossy().into_owned(),
+            })
+            .collect();
+        releases.push(ReleaseDef {
+            tag_name,
+            assets,
+            published_at,
+        });
+    }
+    Ok(releases)
+}
+
+fn find_mirror_release(
+    client: &mut Client,
+    cache: &MetadataCache,
+    base: &str,
+    version_req: &VersionReq,
 ) -> Result<Release, Error> {
-    match version_req {
-        VersionReq::Latest => get_latest_release(client),
-        VersionReq::Nightly => get_nightly_release(client),
-        VersionReq::Stable(_) => find_release_by_req(client, &version_req),
-        VersionReq::ExactStable(_) => get_exact_release(client, version_req),
+    let mut nightly = None;
+    let mut releases = Vec::new();
+    for release_def in list_mirror_releases(client, cache, base)? {
+        match Version::from_git_tag(&release_def.tag_name) {
+            Ok(Version::Nightly(_)) => nightly = Some(release_def),
+            Ok(version) => releases.push(Release {
+                version,
+                assets: release_def.assets,
+            }),
+            Err(_) => continue,
+        }
+    }
+
+    let latest_stable = releases
+        .iter()
+        .filter(|release| Channel::Stable.matches(&release.version))
+        .map(|release| release.version.clone())
+        .max();
+
+    let release = match version_req {
+        VersionReq::Channel(Channel::Nightly) | VersionReq::ExactNightly(_) => {
+            match (nightly, latest_stable) {
+                (Some(release_def), Some(latest)) => Some(Release {
+                    version: Version::Nightly(None)
+                        .configure_nightly(latest, release_def.published_at),
+                    assets: release_def.assets,
+                }),
+                _ => None,
+            }
+            .filter(|release| version_req.matches(&release.version))
+        }
+        _ => releases
+            .into_iter()
+            .filter(|release| version_req.matches(&release.version))
+            .max_by(|a, b| a.version.cmp(&b.version)),
+    };
+
+    release.ok_or_else(|| Error::ReleaseNotFound(version_req.clone()))
+}
+
+impl ReleaseSource {
+    pub fn find_matching_release(
+        &self,
+        client: &mut Client,
+        cache: &MetadataCache,
+        version_req: VersionReq,
+    ) -> Result<Release, Error> {
+        let releases_url = match self {
+            ReleaseSource::GitHub { api, owner, repo } => {
+                format!("{}/repos/{}/{}/releases", api, owner, repo)
+            }
+            ReleaseSource::Mirror(base) => {
+                return find_mirror_release(client, cache, base, &version_req)
+            }
+        };
+
+        match version_req {
+            VersionReq::Channel(Channel::Stable) => {
+                get_latest_release(client, cache, &releases_url)
+            }
+            VersionReq::Channel(Channel::Nightly) => {
+                get_nightly_release(client, cache, &releases_url)
+            }
+            // only the current nightly build is published
+            VersionReq::ExactNightly(_) => get_nightly_release(client, cache, &releases_url)
+                .and_then(|release| {
+                    if version_req.matches(&release.version) {
+                        Ok(release)
+                    } else {
+                        Err(Error::ReleaseNotFound(version_req))
+                    }
+                }),
+            VersionReq::Channel(_) | VersionReq::Stable(_) => {
+                find_release_by_req(client, cache, &releases_url, &version_req)
+            }
+            VersionReq::ExactStable(_) => {
+                get_exact_release(client, cache, &releases_url, version_req)
+            }
+            // custom releases are only available locally
+            VersionReq::Custom(_) => Err(Error::ReleaseNotFound(version_req)),
+        }
+    }
+}
+
+impl Default for ReleaseSource {
+    fn default() -> Self {
+        ReleaseSource::GitHub {
+            api: GITHUB_API_URL.to_string(),
+            owner: "input-output-hk".to_string(),
+            repo: "jormungandr".to_string(),
+        }
     }
 }
 
+impl FromStr for ReleaseSource {
+    type Err = ReleaseSourceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let github = match s.strip_prefix("github:") {
+            Some(github) => github.trim_end_matches('/'),
+            None => return Ok(ReleaseSource::Mirror(s.to_string())),
+        };
+
+        let mut parts = github.rsplitn(3, '/');
+        let repo = parts.next().filter(|repo| !repo.is_empty());
+        let owner = parts.next().filter(|owner| !owner.is_empty());
+        let api = parts.next().unwrap_or(GITHUB_API_URL);
+        match (owner, repo) {
+            (Some(owner), Some(repo)) => Ok(ReleaseSource::GitHub {
+                api: api.to_string(),
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+            }),
+            _ => Err(ReleaseSourceError(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for ReleaseSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReleaseSource::GitHub { api, owner, repo } if api == GITHUB_API_URL => {
+                write!(f, "github:{}/{}", owner, repo)
+            }
+            ReleaseSource::GitHub { api, owner, repo } => {
+                write!(f, "github:{}/{}/{}", api, owner, repo)
+            }
+            ReleaseSource::Mirror(base) => f.write_str(base),
+        }
+    }
+}
+
+impl TryFrom<String> for ReleaseSource {
+    type Error = ReleaseSourceError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<ReleaseSource> for String {
+    fn from(source: ReleaseSource) -> Self {
+        source.to_string()
+    }
+}
+
+/// Find a release of jorup itself. If no version is provided the latest
+/// release is returned.
+pub fn find_jorup_release(
+    client: &mut Client,
+    cache: &MetadataCache,
+    version: Option<Version>,
+) -> Result<Release, Error> {
+    let url = match &version {
+        Some(version) => format!("{}/tags/{}", JORUP_RELEASES_URL, version.to_git_tag()),
+        None => format!("{}/latest", JORUP_RELEASES_URL),
+    };
+    let release_def = download_release_by_url(client, cache, &url)?;
+    let version = Version::from_git_tag(&release_def.tag_name)?;
+    Ok(Release {
+        version,
+        assets: release_def.assets,
+    })
+}
+
 impl Release {
-    pub fn get_asset_url(&self, platform: &str) -> Option<&str> {
+    fn get_asset(&self, platform: &str) -> Option<&AssetDef> {
         let expected_name_part = format!("{}-generic", platform);
-        let maybe_asset = self
-            .assets
+        self.assets.iter().find(|asset| {
+            asset.name.contains(&expected_name_part) && !asset.name.ends_with(CHECKSUM_SUFFIX)
+        })
+    }
+
+    pub fn get_asset_url(&self, platform: &str) -> Option<&str> {
+        self.get_asset(platform).map(|asset| &asset.url[..])
+    }
+
+    /// Get the URL of the SHA-256 checksum published alongside the asset for
+    /// the given platform, if any.
+    pub fn get_asset_checksum_url(&self, platform: &str) -> Option<&str> {
+        let checksum_name = format!("{}{}", self.get_asset(platform)?.name, CHECKSUM_SUFFIX);
+        self.get_asset_url_by_name(&checksum_name)
+    }
+
+    pub fn get_asset_url_by_name(&self, name: &str) -> Option<&str> {
+        self.assets
             .iter()
-            .find(|asset| asset.name.contains(&expected_name_part));
-        maybe_asset.map(|asset| &asset.url[..])
+            .find(|asset| asset.name == name)
+            .map(|asset| &asset.url[..])
     }
 
     pub fn version(&self) -> &Version {
         &self.version
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(source: &str) -> ReleaseSource {
+        source.parse().unwrap()
+    }
+
+    fn github(api: &str, owner: &str, repo: &str) -> ReleaseSource {
+        ReleaseSource::GitHub {
+            api: api.to_string(),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+        }
+    }
+
+    #[test]
+    fn github_repository() {
+        let parsed = source("github:input-output-hk/jormungandr");
+        assert_eq!(parsed, ReleaseSource::default());
+        assert_eq!(parsed.to_string(), "github:input-output-hk/jormungandr");
+        assert_eq!(
+            source("github:me/fork/"),
+            github(GITHUB_API_URL, "me", "fork")
+        );
+    }
+
+    #[test]
+    fn github_api() {
+        let parsed = source("github:https://github.example.com/api/v3/me/fork");
+        assert_eq!(
+            parsed,
+            github("https://github.example.com/api/v3", "me", "fork")
+        );
+        assert_eq!(
+            parsed.to_string(),
+            "github:https://github.example.com/api/v3/me/fork"
+        );
+    }
+
+    #[test]
+    fn invalid_github() {
+        assert!("github:".parse::<ReleaseSource>().is_err());
+        assert!("github:jormungandr".parse::<ReleaseSource>().is_err());
+        assert!("github:/jormungandr".parse::<ReleaseSource>().is_err());
+    }
+
+    #[test]
+    fn mirror() {
+        assert_eq!(
+            source("https://mirror.example.com/jormungandr"),
+            ReleaseSource::Mirror("https://mirror.example.com/jormungandr".to_string())
+        );
+        assert_eq!(source("/srv/jormungandr").to_string(), "/srv/jormungandr");
+    }
+}
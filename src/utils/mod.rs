@@ -1,7 +1,10 @@
 pub mod blockchain;
+pub mod cache;
 pub mod download;
 pub mod github;
 pub mod jcli;
+pub mod overrides;
 pub mod release;
+pub mod rest;
 pub mod runner;
 pub mod version;
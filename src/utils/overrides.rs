@@ -0,0 +1,145 @@
+use crate::{
+    common::JorupConfig,
+    utils::version::{ReqParseError, VersionReq},
+};
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// Name of the file pinning the version of jormungandr for a directory and
+/// its subdirectories
+pub const OVERRIDE_FILE: &str = "jorup-toolchain";
+/// Environment variable overriding the version of jormungandr
+pub const OVERRIDE_ENV: &str = "JORUP_VERSION";
+
+/// A version requirement taking precedence over the requirement of the
+/// blockchain.
+pub struct Override {
+    version_req: VersionReq,
+    source: OverrideSource,
+}
+
+/// Where an override is defined, in order of precedence
+#[derive(Debug, Clone)]
+pub enum OverrideSource {
+    /// the `JORUP_VERSION` environment variable
+    Environment,
+    /// an override set with `jorup override set` for this directory
+    Directory(PathBuf),
+    /// a `jorup-toolchain` file
+    File(PathBuf),
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Cannot get the current directory")]
+    CurrentDir(#[source] io::Error),
+    #[error("Cannot read the overrides: {1}")]
+    CannotReadFile(#[source] io::Error, PathBuf),
+    #[error("Cannot write the overrides: {1}")]
+    CannotWriteFile(#[source] io::Error, PathBuf),
+    #[error("Cannot parse the overrides: {1}")]
+    Json(#[source] serde_json::Error, PathBuf),
+    #[error("Invalid version '{1}' in override {2}")]
+    InvalidVersion(#[source] ReqParseError, String, OverrideSource),
+}
+
+impl Override {
+    /// Find the override in effect for the current directory.
+    ///
+    /// The `JORUP_VERSION` environment variable comes first, then the
+    /// overrides set with `jorup override set` for the current directory or
+    /// its parents, then the first `jorup-toolchain` file found walking up
+    /// from the current directory.
+    pub fn find(cfg: &JorupConfig) -> Result<Option<Self>, Error> {
+        if let Ok(version) = std::env::var(OVERRIDE_ENV) {
+            return Self::parse(&version, OverrideSource::Environment).map(Some);
+        }
+
+        let current_dir = std::env::current_dir().map_err(Error::CurrentDir)?;
+
+        let overrides = list(cfg)?;
+        for dir in current_dir.ancestors() {
+            if let Some(version) = overrides.get(dir) {
+                return Self::parse(version, OverrideSource::Directory(dir.to_path_buf()))
+                    .map(Some);
+            }
+        }
+
+        for dir in current_dir.ancestors() {
+            let path = dir.join(OVERRIDE_FILE);
+            if path.is_file() {
+                let version = std::fs::read_to_string(&path)
+                    .map_err(|e| Error::CannotReadFile(e, path.clone()))?;
+                return Self::parse(&version, OverrideSource::File(path)).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn parse(version: &str, source: OverrideSource) -> Result<Self, Error> {
+        let version = version.trim();
+        VersionReq::parse_exact(version)
+            .map(|version_req| Override {
+                version_req,
+                source: source.clone(),
+            })
+            .map_err(|e| Error::InvalidVersion(e, version.to_string(), source))
+    }
+
+    pub fn version_req(&self) -> &VersionReq {
+        &self.version_req
+    }
+
+    pub fn source(&self) -> &OverrideSource {
+        &self.source
+    }
+}
+
+/// The overrides set with `jorup override set`, by directory
+pub fn list(cfg: &JorupConfig) -> Result<BTreeMap<PathBuf, String>, Error> {
+    let path = cfg.overrides_file();
+    if !path.is_file() {
+        return Ok(BTreeMap::new());
+    }
+    let file = std::fs::File::open(&path).map_err(|e| Error::CannotReadFile(e, path.clone()))?;
+    serde_json::from_reader(file).map_err(|e| Error::Json(e, path))
+}
+
+fn save(cfg: &JorupConfig, overrides: &BTreeMap<PathBuf, String>) -> Result<(), Error> {
+    let path = cfg.overrides_file();
+    std::fs::write(&path, serde_json::to_string_pretty(overrides).unwrap())
+        .map_err(|e| Error::CannotWriteFile(e, path))
+}
+
+/// Pin the version of jormungandr for the given directory
+pub fn set(cfg: &JorupConfig, dir: &Path, version_req: &VersionReq) -> Result<(), Error> {
+    let mut overrides = list(cfg)?;
+    overrides.insert(dir.to_path_buf(), version_req.to_string());
+    save(cfg, &overrides)
+}
+
+/// Remove the override of the given directory, returns false if there was
+/// none.
+pub fn unset(cfg: &JorupConfig, dir: &Path) -> Result<bool, Error> {
+    let mut overrides = list(cfg)?;
+    if overrides.remove(dir).is_none() {
+        return Ok(false);
+    }
+    save(cfg, &overrides)?;
+    Ok(true)
+}
+
+impl fmt::Display for OverrideSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OverrideSource::Environment => write!(f, "environment variable {}", OVERRIDE_ENV),
+            OverrideSource::Directory(dir) => write!(f, "directory {}", dir.display()),
+            OverrideSource::File(path) => write!(f, "file {}", path.display()),
+        }
+    }
+}
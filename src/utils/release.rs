@@ -1,31 +1,64 @@
 use crate::{
     common::JorupConfig,
     utils::{
+        cache::MetadataCache,
         download::Client,
-        github,
+        github::ReleaseSource,
+        overrides::{self, Override},
         version::{Version, VersionReq},
     },
 };
+use chrono::{DateTime, Local};
 use std::{
+    env::consts::EXE_SUFFIX,
     fs::{self, File},
     io,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
 
-const TARGET: &str = env!("TARGET");
+pub const TARGET: &str = env!("TARGET");
+
+/// The tools installed in the bin directory as proxies to the default or
+/// overridden release
+pub const PROXIES: &[&str] = &["jormungandr", "jcli"];
 
 pub struct Release {
     version: Version,
     path: PathBuf,
 }
 
+pub struct AssetRemote {
+    url: String,
+    checksum_url: Option<String>,
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Cannot read the release directory: {1}")]
     ReleaseDirectory(#[source] io::Error, PathBuf),
     #[error("No compatible release installed, expecting {0}")]
     NoCompatibleReleaseInstalled(VersionReq),
+    #[error("No release installed for {0}, expecting {1}")]
+    NoOverrideReleaseInstalled(String, VersionReq),
+    #[error("Cannot resolve the version override")]
+    Override(#[source] overrides::Error),
+    #[error("Cannot read the default release: {1}")]
+    CannotReadDefault(#[source] io::Error, PathBuf),
+    #[error("Invalid default release '{1}'")]
+    InvalidDefault(#[source] crate::utils::version::SemVerError, String),
+    #[error("Cannot set the default release: {1}")]
+    CannotWriteDefault(#[source] io::Error, PathBuf),
+    #[error("Cannot install the proxy {1}")]
+    CannotCreateProxy(#[source] io::Error, PathBuf),
+    #[error("Cannot link the release to {1}")]
+    CannotLink(#[source] io::Error, PathBuf),
+    #[error("{1} not found in {2}")]
+    MissingTool(#[source] io::Error, String, PathBuf),
+    #[error("{0} is already installed and is not a linked release")]
+    AlreadyInstalled(Version),
+    #[error("{0} is not a linked release")]
+    NotLinked(Version),
     #[error(transparent)]
     GitHub(#[from] crate::utils::github::Error),
     #[error("Error while creating directory: {1}")]
@@ -34,6 +67,16 @@ pub enum Error {
     CannotOpenFile(#[source] io::Error, PathBuf),
     #[error("Asset not found for the current platform")]
     AssetNotFound,
+    #[error("No checksum published for the asset, use `--allow-unverified` to install anyway")]
+    NoChecksum,
+    #[error("Malformed checksum file: {0}")]
+    MalformedChecksum(PathBuf),
+    #[error("Checksum mismatch for {path}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
     #[cfg(unix)]
     #[error("Cannot unpack assset: {1}")]
     CannotUnpack(#[source] io::Error, PathBuf),
@@ -46,34 +89,89 @@ pub fn list_installed_releases(cfg: &JorupConfig) -> Result<impl Iterator<Item =
     Ok(fs::read_dir(cfg.release_dir())
         .map_err(|err| Error::ReleaseDirectory(err, cfg.release_dir()))?
         .filter_map(Result::ok)
+        // linked releases are symbolic links to directories. A directory
+        // without the tools is left over from a failed installation.
         .filter(|entry| {
-            entry
-                .file_type()
-                .map(|etype| etype.is_dir())
-                .unwrap_or_else(|_| false)
+            PROXIES.iter().all(|tool| {
+                entry
+                    .path()
+                    .join(format!("{}{}", tool, EXE_SUFFIX))
+                    .is_file()
+            })
         })
         .filter_map(|entry| {
             entry
                 .file_name()
                 .as_os_str()
                 .to_str()
-                .map(|name| Version::parse(name))
+                .map(|name| Version::parse_name(name))
                 .and_then(Result::ok)
         }))
 }
 
 impl Release {
-    /// load the latest locally installed release
+    /// load the release to use: the latest locally installed release matching
+    /// the override in effect (see `Override::find`) or, without an override,
+    /// matching `version_req`
     pub fn load(cfg: &mut JorupConfig, version_req: &VersionReq) -> Result<Self, Error> {
+        match Self::load_override(cfg)? {
+            Some(release) => Ok(release),
+            None => Self::load_matching(cfg, version_req),
+        }
+    }
+
+    /// load the latest locally installed release matching the override in
+    /// effect, if any
+    pub fn load_override(cfg: &mut JorupConfig) -> Result<Option<Self>, Error> {
+        let version_override = match Override::find(cfg).map_err(Error::Override)? {
+            Some(version_override) => version_override,
+            None => return Ok(None),
+        };
+
+        match Self::load_matching(cfg, version_override.version_req()) {
+            Err(Error::NoCompatibleReleaseInstalled(version_req)) => {
+                Err(Error::NoOverrideReleaseInstalled(
+                    version_override.source().to_string(),
+                    version_req,
+                ))
+            }
+            result => result.map(Some),
+        }
+    }
+
+    /// load the default release, set with `make_default`. A default release
+    /// that is not installed anymore is no default release.
+    pub fn load_default(cfg: &mut JorupConfig) -> Result<Option<Self>, Error> {
+        let path = cfg.default_release_file();
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let version =
+            fs::read_to_string(&path).map_err(|e| Error::CannotReadDefault(e, path.clone()))?;
+        let version = version.trim();
+        let version = Version::parse_name(version)
+            .map_err(|e| Error::InvalidDefault(e, version.to_string()))?;
+        Self::load_installed(cfg, &VersionReq::exact(version))
+    }
+
+    /// load the latest locally installed release
+    pub fn load_matching(cfg: &mut JorupConfig, version_req: &VersionReq) -> Result<Self, Error> {
+        Self::load_installed(cfg, version_req)?.ok_or_else(|| {
+            eprintln!("HINT: run `jorup node install`");
+            Error::NoCompatibleReleaseInstalled(version_req.clone())
+        })
+    }
+
+    /// load the latest locally installed release, if any
+    pub fn load_installed(
+        cfg: &mut JorupConfig,
+        version_req: &VersionReq,
+    ) -> Result<Option<Self>, Error> {
         let version = list_installed_releases(cfg)?
             .filter(|version| version_req.matches(version))
-            .max()
-            .ok_or_else(|| {
-                eprintln!("HINT: run `jorup node install`");
-                Error::NoCompatibleReleaseInstalled(version_req.clone())
-            })?;
+            .max();
 
-        Self::new(cfg, version)
+        version.map(|version| Self::new(cfg, version)).transpose()
     }
 
     pub fn new(cfg: &mut JorupConfig, version: Version) -> Result<Self, Error> {
@@ -83,24 +181,76 @@ impl Release {
         Ok(Release { version, path })
     }
 
+    /// Register the `jormungandr` and `jcli` binaries of a directory as a
+    /// custom release, linking it in the release directory. A release linked
+    /// with the same name is replaced.
+    pub fn link(cfg: &mut JorupConfig, version: Version, dir: &Path) -> Result<Self, Error> {
+        for tool in PROXIES {
+            let binary = dir.join(format!("{}{}", tool, EXE_SUFFIX));
+            fs::metadata(&binary)
+                .map_err(|e| Error::MissingTool(e, tool.to_string(), dir.to_path_buf()))?;
+        }
+
+        let path = cfg.release_dir().join(version.to_string());
+        if path.symlink_metadata().is_ok() {
+            if fs::read_link(&path).is_err() {
+                return Err(Error::AlreadyInstalled(version));
+            }
+            remove_link(&path).map_err(|e| Error::CannotLink(e, dir.to_path_buf()))?;
+        }
+        fs::create_dir_all(cfg.release_dir())
+            .map_err(|e| Error::CannotCreateDirectory(e, cfg.release_dir()))?;
+        create_link(dir, &path).map_err(|e| Error::CannotLink(e, dir.to_path_buf()))?;
+
+        Ok(Release { version, path })
+    }
+
+    /// Remove a release registered with `link`, the linked directory is left
+    /// untouched.
+    pub fn unlink(cfg: &JorupConfig, version: Version) -> Result<(), Error> {
+        let path = cfg.release_dir().join(version.to_string());
+        let dir = fs::read_link(&path).map_err(|_| Error::NotLinked(version.clone()))?;
+        remove_link(&path).map_err(|e| Error::CannotLink(e, dir))?;
+        unset_default(cfg, &version)
+    }
+
+    /// The directory this release is linked to, if it was registered with
+    /// `link`
+    pub fn linked_dir(&self) -> Option<PathBuf> {
+        fs::read_link(&self.path).ok()
+    }
+
+    /// Make this release the one run by the `jormungandr` and `jcli`
+    /// proxies when no version is given or overridden.
     pub fn make_default(&self, cfg: &JorupConfig) -> Result<(), Error> {
-        let bin_dir = cfg.bin_dir();
+        let path = cfg.default_release_file();
+        fs::write(&path, self.version.to_string())
+            .map_err(|e| Error::CannotWriteDefault(e, path))?;
 
-        let install_jormungandr = bin_dir.join("jormungandr");
-        let install_jcli = bin_dir.join("jcli");
+        install_proxies(cfg)
+    }
 
-        create_symlink(self.get_jormungandr(), install_jormungandr).unwrap();
-        create_symlink(self.get_jcli(), install_jcli).unwrap();
+    pub fn version(&self) -> &Version {
+        &self.version
+    }
 
-        Ok(())
+    /// When the release was installed
+    pub fn installed_at(&self) -> io::Result<DateTime<Local>> {
+        let metadata = fs::symlink_metadata(self.dir())?;
+        metadata
+            .created()
+            .or_else(|_| metadata.modified())
+            .map(DateTime::from)
     }
 
-    pub fn get_jormungandr(&self) -> PathBuf {
-        self.dir().join("jormungandr")
+    /// The disk space used by the release, including the downloaded archive
+    pub fn disk_size(&self) -> io::Result<u64> {
+        dir_size(self.dir())
     }
 
-    pub fn get_jcli(&self) -> PathBuf {
-        self.dir().join("jcli")
+    /// The path of the given tool of this release
+    pub fn get_tool(&self, tool: &str) -> PathBuf {
+        self.dir().join(format!("{}{}", tool, EXE_SUFFIX))
     }
 
     #[cfg(windows)]
@@ -113,12 +263,21 @@ impl Release {
         self.dir().join("archive.tar.gz")
     }
 
+    pub fn get_asset_checksum(&self) -> PathBuf {
+        let mut name = self.get_asset().into_os_string();
+        name.push(".sha256");
+        PathBuf::from(name)
+    }
+
+    /// Whether the archive needs to be downloaded to install the release. The
+    /// archive of an installed release may have been removed by
+    /// `jorup node prune`.
     pub fn asset_need_fetched(&self) -> bool {
-        !self.get_asset().is_file()
+        self.asset_need_open() && !self.get_asset().is_file()
     }
 
     pub fn asset_need_open(&self) -> bool {
-        !self.get_jormungandr().is_file() || !self.get_jcli().is_file()
+        PROXIES.iter().any(|tool| !self.get_tool(tool).is_file())
     }
 
     pub fn asset_open(&self) -> Result<(), Error> {
@@ -163,11 +322,51 @@ impl Release {
         Ok(())
     }
 
-    pub fn asset_remote(&self, client: &mut Client) -> Result<String, Error> {
+    /// Check the downloaded asset against the checksum file stored next to
+    /// it. The checksum file can either contain the bare hex digest or use the
+    /// `sha256sum` output format.
+    pub fn asset_verify(&self) -> Result<(), Error> {
+        use sha2::{Digest, Sha256};
+
+        let checksum_path = self.get_asset_checksum();
+        let checksum = fs::read_to_string(&checksum_path)
+            .map_err(|e| Error::CannotOpenFile(e, checksum_path.clone()))?;
+        let expected = checksum
+            .split_whitespace()
+            .next()
+            .ok_or_else(|| Error::MalformedChecksum(checksum_path.clone()))?
+            .to_lowercase();
+
+        let mut file =
+            File::open(self.get_asset()).map_err(|e| Error::CannotOpenFile(e, self.get_asset()))?;
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).map_err(|e| Error::CannotOpenFile(e, self.get_asset()))?;
+        let actual = hex::encode(hasher.result());
+
+        if actual != expected {
+            return Err(Error::ChecksumMismatch {
+                path: self.get_asset(),
+                expected,
+                actual,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn asset_remote(
+        &self,
+        client: &mut Client,
+        cache: &MetadataCache,
+        source: &ReleaseSource,
+    ) -> Result<AssetRemote, Error> {
         let release =
-            github::find_matching_release(client, VersionReq::exact(self.version.clone()))?;
+            source.find_matching_release(client, cache, VersionReq::exact(self.version.clone()))?;
         match release.get_asset_url(TARGET) {
-            Some(url) => Ok(url.to_string()),
+            Some(url) => Ok(AssetRemote {
+                url: url.to_string(),
+                checksum_url: release.get_asset_checksum_url(TARGET).map(str::to_string),
+            }),
             None => Err(Error::AssetNotFound),
         }
     }
@@ -177,12 +376,130 @@ impl Release {
     }
 }
 
+impl AssetRemote {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn checksum_url(&self) -> Option<&str> {
+        self.checksum_url.as_deref()
+    }
+}
+
+fn dir_size(path: &Path) -> io::Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        size += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(size)
+}
+
+/// Install the `jormungandr` and `jcli` proxies in the bin directory,
+/// replacing the previous proxies or the symbolic links to a release made by
+/// the previous versions of jorup.
+pub fn install_proxies(cfg: &JorupConfig) -> Result<(), Error> {
+    let jorup = cfg.bin_dir().join(format!("jorup{}", EXE_SUFFIX));
+    let jorup = if jorup.is_file() {
+        jorup
+    } else {
+        std::env::current_exe().map_err(|e| Error::CannotCreateProxy(e, jorup))?
+    };
+
+    for tool in PROXIES {
+        let proxy = cfg.bin_dir().join(format!("{}{}", tool, EXE_SUFFIX));
+        if proxy.symlink_metadata().is_err() {
+            create_proxy(&jorup, &proxy).map_err(|e| Error::CannotCreateProxy(e, proxy))?;
+            continue;
+        }
+
+        migrate_default(cfg, &proxy)?;
+        fs::remove_file(&proxy)
+            .and_then(|()| create_proxy(&jorup, &proxy))
+            .map_err(|e| Error::CannotCreateProxy(e, proxy))?;
+    }
+
+    Ok(())
+}
+
+/// Unset the default release if it is `version`, once it was removed
+pub fn unset_default(cfg: &JorupConfig, version: &Version) -> Result<(), Error> {
+    let path = cfg.default_release_file();
+    if !path.is_file() {
+        return Ok(());
+    }
+    let default =
+        fs::read_to_string(&path).map_err(|e| Error::CannotReadDefault(e, path.clone()))?;
+    if default.trim() != version.to_string() {
+        return Ok(());
+    }
+    fs::remove_file(&path).map_err(|e| Error::CannotWriteDefault(e, path))
+}
+
+/// Keep the default release of a symbolic link made by a previous version of
+/// jorup to a release, if no default release is set yet.
+fn migrate_default(cfg: &JorupConfig, link: &Path) -> Result<(), Error> {
+    let path = cfg.default_release_file();
+    if path.is_file() {
+        return Ok(());
+    }
+    let version = fs::read_link(link)
+        .ok()
+        .and_then(|target| {
+            target
+                .strip_prefix(cfg.release_dir())
+                .ok()
+                .and_then(|release| release.iter().next())
+                .map(|version| version.to_string_lossy().into_owned())
+        })
+        .filter(|version| Version::parse(version).is_ok());
+    match version {
+        Some(version) => fs::write(&path, version).map_err(|e| Error::CannotWriteDefault(e, path)),
+        None => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn create_proxy(jorup: &Path, proxy: &Path) -> io::Result<()> {
+    // a relative link keeps working if the jorup home is moved
+    let target = if jorup.parent() == proxy.parent() {
+        Path::new(jorup.file_name().unwrap())
+    } else {
+        jorup
+    };
+    std::os::unix::fs::symlink(target, proxy)
+}
+
+/// Creating symbolic links requires extra privileges on Windows, a hard link
+/// or a copy of jorup is used instead.
+#[cfg(windows)]
+fn create_proxy(jorup: &Path, proxy: &Path) -> io::Result<()> {
+    fs::hard_link(jorup, proxy).or_else(|_| fs::copy(jorup, proxy).map(|_| ()))
+}
+
+#[cfg(unix)]
+fn create_link(dir: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(dir, link)
+}
+
+/// Creating symbolic links requires extra privileges on Windows, such as the
+/// developer mode.
+#[cfg(windows)]
+fn create_link(dir: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(dir, link)
+}
+
 #[cfg(unix)]
-fn create_symlink<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    std::os::unix::fs::symlink(src, dst)
+fn remove_link(link: &Path) -> io::Result<()> {
+    fs::remove_file(link)
 }
 
 #[cfg(windows)]
-fn create_symlink<P: AsRef<Path>, Q: AsRef<Path>>(src: P, dst: Q) -> io::Result<()> {
-    std::os::windows::fs::symlink_file(src, dst)
+fn remove_link(link: &Path) -> io::Result<()> {
+    fs::remove_dir(link)
 }
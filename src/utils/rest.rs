@@ -0,0 +1,118 @@
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+
+/// How long to wait for the node to answer, it only listens locally
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Client of the REST API of a running jormungandr node
+pub struct RestClient {
+    inner: reqwest::blocking::Client,
+    base_url: String,
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to create the REST client")]
+    NewClient(#[source] reqwest::Error),
+    #[error("Cannot reach the node REST API at {1}")]
+    Request(#[source] reqwest::Error, String),
+    #[error("The node REST API answered {0} for {1}")]
+    Status(StatusCode, String),
+    #[error("Invalid answer of the node REST API for {1}")]
+    Json(#[source] serde_json::Error, String),
+}
+
+/// The statistics of the node, from `/v0/node/stats`. Until the node is
+/// running, only its version and state are known.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeStats {
+    pub version: String,
+    pub state: String,
+    pub uptime: Option<u64>,
+    pub block_recv_cnt: Option<u64>,
+    pub tx_recv_cnt: Option<u64>,
+    pub last_block_date: Option<String>,
+    pub last_block_hash: Option<String>,
+    pub last_block_height: Option<String>,
+    pub last_block_time: Option<String>,
+    pub last_block_tx: Option<u64>,
+    pub last_received_block_time: Option<String>,
+    pub peer_connected_cnt: Option<u64>,
+    pub peer_total_cnt: Option<u64>,
+}
+
+/// The settings of the blockchain, from `/v0/settings`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeSettings {
+    pub block0_hash: String,
+    pub block0_time: String,
+    pub consensus_version: String,
+    pub fees: Fees,
+    pub slot_duration: u64,
+    pub slots_per_epoch: u64,
+    pub max_txs_per_block: Option<u64>,
+    pub epoch_stability_depth: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Fees {
+    pub constant: u64,
+    pub coefficient: u64,
+    pub certificate: u64,
+}
+
+impl RestClient {
+    /// Client of a node listening on the given local port
+    pub fn new(rest_port: u16) -> Result<Self, Error> {
+        let inner = reqwest::blocking::ClientBuilder::new()
+            .timeout(TIMEOUT)
+            .build()
+            .map_err(Error::NewClient)?;
+        Ok(RestClient {
+            inner,
+            base_url: format!("http://127.0.0.1:{}/api", rest_port),
+        })
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub fn stats(&self) -> Result<NodeStats, Error> {
+        self.get_json("v0/node/stats")
+    }
+
+    pub fn settings(&self) -> Result<NodeSettings, Error> {
+        self.get_json("v0/settings")
+    }
+
+    /// Ask the node to shut down, it returns before the node has stopped
+    pub fn shutdown(&self) -> Result<(), Error> {
+        self.get("v0/shutdown").map(|_| ())
+    }
+
+    fn get(&self, path: &str) -> Result<(String, Vec<u8>), Error> {
+        let url = format!("{}/{}", self.base_url, path);
+        let response = self
+            .inner
+            .get(&url)
+            .send()
+            .map_err(|e| Error::Request(e, url.clone()))?;
+        if !response.status().is_success() {
+            return Err(Error::Status(response.status(), url));
+        }
+        let body = response
+            .bytes()
+            .map_err(|e| Error::Request(e, url.clone()))?;
+        Ok((url, body.to_vec()))
+    }
+
+    fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let (url, body) = self.get(path)?;
+        serde_json::from_slice(&body).map_err(|e| Error::Json(e, url))
+    }
+}
@@ -1,10 +1,18 @@
-use crate::utils::blockchain::Blockchain;
+use crate::utils::{
+    blockchain::{self, Blockchain},
+    rest::RestClient,
+};
+use chrono::{DateTime, Local, Utc};
 use serde::{Deserialize, Serialize};
 use std::{
+    fmt,
+    fs::{File, OpenOptions},
     io,
     net::SocketAddr,
-    path::PathBuf,
-    process::{Command, Stdio},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    str::FromStr,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
@@ -15,6 +23,65 @@ pub struct RunnerInfo {
     rest_port: Option<u16>,
     jcli: PathBuf,
     jormungandr: PathBuf,
+    /// when the node was started, missing from the runner files of previous
+    /// versions of jorup
+    #[serde(default)]
+    started_at: Option<DateTime<Utc>>,
+    /// the resolved path of the running jormungandr binary
+    #[serde(default)]
+    exe: Option<PathBuf>,
+    /// the process restarting the node, with `jorup run --daemon --restart`
+    #[serde(default)]
+    supervisor: Option<SupervisorInfo>,
+}
+
+/// The `jorup supervise` process restarting the node, see `supervise`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SupervisorInfo {
+    pid: u32,
+    exe: Option<PathBuf>,
+    started_at: DateTime<Utc>,
+    restart: RestartPolicy,
+    max_restarts: u32,
+    /// the number of times the node was restarted
+    restarts: u32,
+    /// how the previous node exited
+    last_exit_status: Option<String>,
+}
+
+/// When the supervisor starts the node again after it exited
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// only when the node failed: it exited with an error or was killed
+    OnFailure,
+    /// whatever the exit status of the node
+    Always,
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid restart policy '{0}', expected on-failure or always")]
+pub struct InvalidRestartPolicy(String);
+
+/// How the supervisor restarts the node
+#[derive(Clone, Copy, Debug)]
+pub struct Restart {
+    pub policy: RestartPolicy,
+    /// the number of restarts in a row after which the supervisor gives up
+    pub max_restarts: u32,
+}
+
+/// The node started, and restarted, by `supervise`
+#[derive(Debug)]
+pub struct Supervision {
+    pub dir: PathBuf,
+    pub jcli: PathBuf,
+    pub jormungandr: PathBuf,
+    pub arguments: Vec<String>,
+    pub rest_port: Option<u16>,
+    pub keep_logs: usize,
+    pub restart: Restart,
 }
 
 pub struct RunnerControl<'a> {
@@ -34,6 +101,8 @@ pub enum Error {
     Json(#[source] serde_json::Error, PathBuf),
     #[error("Cannot remove running file")]
     CannotRemoveRunnerFile(#[source] io::Error),
+    #[error("Cannot rotate the logs of the previous node")]
+    CannotRotateLogs(#[source] io::Error),
     #[error("Cannot start jormungandr")]
     CannotStartJormungandr(#[source] io::Error),
     #[error("No running node")]
@@ -46,12 +115,24 @@ pub enum Error {
     PidCheck(#[source] io::Error),
     #[error("Node already running. PID: {0}")]
     NodeRunning(u32),
-    #[error("Cannot stop running node")]
-    CannotStopNode,
-    #[error("Cannot send shutdown signal to the running node")]
-    CannotSendStopSignal(#[source] io::Error),
-    #[error("REST is not running")]
-    RestNotRunning,
+    #[error("The node is still running (PID: {0}), use `--force` to kill it")]
+    NodeStillRunning(u32),
+    #[error("Cannot kill the node (PID: {0})")]
+    CannotKillNode(u32),
+    #[error("Cannot send a signal to the node")]
+    CannotSignalNode(#[source] io::Error),
+    #[error("Cannot start the supervisor")]
+    CannotStartSupervisor(#[source] io::Error),
+    #[error("The supervisor exited before starting the node ({0}), check {1}")]
+    SupervisorExited(ExitStatus, PathBuf),
+    #[error("The supervisor (PID: {0}) did not start the node, check {1}")]
+    SupervisorNotStarted(u32, PathBuf),
+    #[error("Cannot stop the supervisor (PID: {0})")]
+    CannotStopSupervisor(u32),
+    #[error("Cannot wait for the node to exit")]
+    CannotWaitNode(#[source] io::Error),
+    #[error("The node exited {0} times in a row, not restarting it again")]
+    TooManyRestarts(u32),
 }
 
 impl<'a> RunnerControl<'a> {
@@ -59,12 +140,9 @@ impl<'a> RunnerControl<'a> {
         let info_file = blockchain.get_runner_file();
 
         if info_file.is_file() {
-            let info = std::fs::read_to_string(&info_file)
-                .map_err(|e| Error::CannotOpenFile(e, info_file.clone()))?;
-            let info: RunnerInfo =
-                serde_json::from_str(&info).map_err(|e| Error::Json(e, info_file))?;
+            let info = RunnerInfo::load(&info_file)?;
 
-            let is_up = check_pid(info.pid)?;
+            let is_up = info.is_running()?;
 
             if is_up {
                 return Err(Error::NodeRunning(info.pid));
@@ -95,12 +173,9 @@ impl<'a> RunnerControl<'a> {
             return Err(Error::NoRunningNode);
         }
 
-        let info = std::fs::read_to_string(&info_file)
-            .map_err(|e| Error::CannotOpenFile(e, info_file.clone()))?;
-        let info: RunnerInfo =
-            serde_json::from_str(&info).map_err(|e| Error::Json(e, info_file))?;
+        let info = RunnerInfo::load(&info_file)?;
 
-        let is_up = check_pid(info.pid)?;
+        let is_up = info.is_running()?;
 
         if !is_up {
             return Err(Error::NoRunningNode);
@@ -117,108 +192,171 @@ impl<'a> RunnerControl<'a> {
         });
     }
 
-    pub fn jcli(&self) -> Command {
-        Command::new(&self.jcli)
-    }
-
-    pub fn jormungandr(&self) -> Command {
-        Command::new(&self.jormungandr)
-    }
-
-    fn prepare(
-        &mut self,
+    /// The arguments of the node, besides the extra parameters given by the
+    /// user
+    fn arguments(
+        &self,
         default_config: bool,
         rest_addr: Option<SocketAddr>,
-    ) -> Result<Command, Error> {
+    ) -> Result<Vec<String>, Error> {
         let blockchain = self.blockchain;
 
         if let Some(info) = &self.info {
             return Err(Error::NodeRunning(info.pid));
         }
 
-        let mut cmd = self.jormungandr();
-
-        cmd.current_dir(blockchain.dir());
+        let mut args = Vec::new();
 
         if let Some(rest_addr) = rest_addr {
-            cmd.args(&["--rest-listen", &rest_addr.to_string()]);
+            args.push("--rest-listen".to_string());
+            args.push(rest_addr.to_string());
         }
 
         if default_config {
             let genesis_block_hash =
                 std::fs::read_to_string(blockchain.get_genesis_block_hash()).unwrap();
 
-            cmd.args(&[
-                "--storage",
-                blockchain.get_node_storage().display().to_string().as_str(),
-                "--genesis-block-hash",
-                &genesis_block_hash,
-            ]);
+            args.push("--storage".to_string());
+            args.push(blockchain.get_node_storage().display().to_string());
+            args.push("--genesis-block-hash".to_string());
+            args.push(genesis_block_hash);
 
             for peer in blockchain.entry().trusted_peers() {
-                cmd.args(&[
-                    "--trusted-peer",
-                    &format!("{}@{}", peer.address(), peer.id()),
-                ]);
+                args.push("--trusted-peer".to_string());
+                args.push(format!("{}@{}", peer.address(), peer.id()));
             }
 
             if blockchain.get_node_secret().is_file() {
-                cmd.args(&[
-                    "--secret",
-                    blockchain.get_node_secret().display().to_string().as_str(),
-                ]);
+                args.push("--secret".to_string());
+                args.push(blockchain.get_node_secret().display().to_string());
             }
         }
 
-        Ok(cmd)
+        Ok(args)
     }
 
+    /// Start the node in the background, logging to the log file of the
+    /// blockchain. The log of the previous node is rotated first, keeping
+    /// `keep_logs` previous logs.
+    ///
+    /// With `restart`, the node is started by a `jorup supervise` process
+    /// which restarts it when it exits, see `supervise`.
     pub fn spawn(
         &mut self,
         default_config: bool,
         rest_addr: Option<SocketAddr>,
         parameters: Vec<String>,
+        keep_logs: usize,
+        restart: Option<Restart>,
     ) -> Result<(), Error> {
-        let mut cmd = self.prepare(default_config, rest_addr)?;
-        cmd.args(parameters);
-
-        cmd.stdin(Stdio::null());
-        cmd.stdout(Stdio::null());
-        cmd.stderr(
-            std::fs::File::create(self.blockchain.get_log_file())
-                .map_err(|e| Error::CannotOpenFile(e, self.blockchain.get_log_file()))?,
-        );
+        let mut arguments = self.arguments(default_config, rest_addr)?;
+        arguments.extend(parameters);
+        let rest_port = rest_addr.as_ref().map(|rest| rest.port());
+
+        if let Some(restart) = restart {
+            return self.spawn_supervisor(Supervision {
+                dir: self.blockchain.dir().clone(),
+                jcli: self.jcli.clone(),
+                jormungandr: self.jormungandr.clone(),
+                arguments,
+                rest_port,
+                keep_logs,
+                restart,
+            });
+        }
 
-        let child = cmd.spawn().map_err(Error::CannotStartJormungandr)?;
+        let child = start_node(
+            self.blockchain.dir(),
+            &self.jormungandr,
+            &arguments,
+            keep_logs,
+        )?;
 
-        let runner_info = RunnerInfo {
-            pid: child.id(),
-            rest_port: rest_addr.as_ref().map(|rest| rest.port()),
-            jcli: self.jcli.clone(),
-            jormungandr: self.jormungandr.clone(),
-        };
+        let runner_info = RunnerInfo::started(&child, rest_port, &self.jcli, &self.jormungandr);
 
-        std::fs::write(
-            self.blockchain.get_runner_file(),
-            serde_json::to_string(&runner_info).unwrap(),
-        )
         // TODO? on failure, shall we kill the child?
-        .map_err(|e| Error::CannotWriteFile(e, self.blockchain.get_runner_file()))?;
+        runner_info.save(&self.blockchain.get_runner_file())?;
 
         self.info = Some(runner_info);
 
         Ok(())
     }
 
+    /// Start `jorup supervise` in the background and wait for it to start
+    /// the node. The supervisor logs to the supervisor log of the blockchain.
+    fn spawn_supervisor(&mut self, supervision: Supervision) -> Result<(), Error> {
+        let jorup = std::env::current_exe().map_err(Error::CannotStartSupervisor)?;
+        let log_file = self.blockchain.get_supervisor_log_file();
+        let log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&log_file)
+            .map_err(|e| Error::CannotOpenFile(e, log_file.clone()))?;
+
+        // parsed by the `supervise` command
+        let mut cmd = Command::new(jorup);
+        cmd.arg("supervise")
+            .arg("--dir")
+            .arg(&supervision.dir)
+            .arg("--jcli")
+            .arg(&supervision.jcli)
+            .arg("--jormungandr")
+            .arg(&supervision.jormungandr)
+            .args(["--keep-logs", &supervision.keep_logs.to_string()])
+            .args(["--restart", &supervision.restart.policy.to_string()])
+            .args([
+                "--max-restarts",
+                &supervision.restart.max_restarts.to_string(),
+            ]);
+        if let Some(rest_port) = supervision.rest_port {
+            cmd.args(["--rest-port", &rest_port.to_string()]);
+        }
+        cmd.arg("--").args(&supervision.arguments);
+
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::null());
+        cmd.stderr(log);
+
+        let mut supervisor = cmd.spawn().map_err(Error::CannotStartSupervisor)?;
+
+        let runner_file = self.blockchain.get_runner_file();
+        let start = Instant::now();
+        loop {
+            if let Ok(info) = RunnerInfo::load(&runner_file) {
+                if info.supervisor().map(|supervisor| supervisor.pid) == Some(supervisor.id()) {
+                    self.info = Some(info);
+                    return Ok(());
+                }
+            }
+            if let Some(status) = supervisor
+                .try_wait()
+                .map_err(Error::CannotStartSupervisor)?
+            {
+                return Err(Error::SupervisorExited(status, log_file));
+            }
+            if start.elapsed() >= SUPERVISOR_START_TIMEOUT {
+                // do not leave a supervisor behind that nothing knows about
+                let _ = supervisor.kill();
+                let _ = supervisor.wait();
+                return Err(Error::SupervisorNotStarted(supervisor.id(), log_file));
+            }
+            std::thread::sleep(Duration::from_millis(100));
+        }
+    }
+
     pub fn run(
-        mut self,
+        self,
         default_config: bool,
         rest_addr: Option<SocketAddr>,
         parameters: Vec<String>,
     ) -> Result<(), Error> {
-        let mut cmd = self.prepare(default_config, rest_addr)?;
-        cmd.args(parameters);
-        let mut child = cmd.spawn().map_err(Error::CannotStartJormungandr)?;
+        let arguments = self.arguments(default_config, rest_addr)?;
+        let mut child = Command::new(&self.jormungandr)
+            .current_dir(self.blockchain.dir())
+            .args(arguments)
+            .args(parameters)
+            .spawn()
+            .map_err(Error::CannotStartJormungandr)?;
 
         child
             .wait()
@@ -226,116 +364,525 @@ impl<'a> RunnerControl<'a> {
             .map_err(|e| panic!("failed to wait for exit: {}", e))
     }
 
-    pub fn shutdown(&mut self) -> Result<(), Error> {
-        let info = if let Some(info) = std::mem::replace(&mut self.info, None) {
-            info
-        } else {
-            return Ok(());
+    /// The runner file of the running node
+    pub fn info(&self) -> Option<&RunnerInfo> {
+        self.info.as_ref()
+    }
+
+    /// Stop the node and remove its runner file once it has exited, see
+    /// `RunnerInfo::shutdown`
+    pub fn shutdown(&mut self, timeout: Duration, force: bool) -> Result<(), Error> {
+        let info = match &self.info {
+            Some(info) => info.clone(),
+            None => return Ok(()),
         };
 
-        let status = self
-            .jcli()
-            .args(&[
-                "rest",
-                "v0",
-                "shutdown",
-                "get",
-                "--host",
-                &format!(
-                    "http://localhost:{}/api",
-                    info.rest_port.ok_or(Error::RestNotRunning)?
-                ),
-            ])
-            .status()
-            .map_err(Error::CannotSendStopSignal)?;
-
-        if status.success() {
-            std::fs::remove_file(self.blockchain.get_runner_file())
-                .map_err(Error::CannotRemoveRunnerFile)
-        } else {
-            Err(Error::CannotStopNode)
+        info.shutdown(&self.blockchain.get_runner_file(), timeout, force)?;
+        self.info = None;
+        Ok(())
+    }
+}
+
+/// How long to wait for the node to exit once killed
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the supervisor to start the node
+const SUPERVISOR_START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long the supervisor waits before the first restart, the delay doubles
+/// with every failure in a row up to `MAX_BACKOFF`
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// A node running for this long is considered healthy: the next failure
+/// starts again with `INITIAL_BACKOFF` and does not count toward the maximum
+/// number of restarts
+const HEALTHY_RUN: Duration = Duration::from_secs(600);
+
+/// Start the node and start it again when it exits, according to the restart
+/// policy, until it failed `max_restarts` times in a row.
+///
+/// This runs in the `jorup supervise` process spawned by
+/// `RunnerControl::spawn`, and its messages go to the supervisor log of the
+/// blockchain. The runner file is rewritten every time the node is started
+/// or exits, with the restart count and the last exit status.
+pub fn supervise(supervision: Supervision) -> Result<(), Error> {
+    let Supervision {
+        dir,
+        jcli,
+        jormungandr,
+        arguments,
+        rest_port,
+        keep_logs,
+        restart,
+    } = supervision;
+    let runner_file = dir.join(blockchain::RUNNER_FILE);
+
+    let mut supervisor = SupervisorInfo {
+        pid: std::process::id(),
+        exe: std::env::current_exe().and_then(std::fs::canonicalize).ok(),
+        started_at: Utc::now(),
+        restart: restart.policy,
+        max_restarts: restart.max_restarts,
+        restarts: 0,
+        last_exit_status: None,
+    };
+    let mut backoff = INITIAL_BACKOFF;
+    let mut failures = 0;
+
+    loop {
+        let mut child = start_node(&dir, &jormungandr, &arguments, keep_logs)?;
+        let started = Instant::now();
+        let mut info = RunnerInfo::started(&child, rest_port, &jcli, &jormungandr);
+        info.supervisor = Some(supervisor.clone());
+        info.save(&runner_file)?;
+        supervisor_log(format_args!("node started (PID: {})", child.id()));
+
+        let status = child.wait().map_err(Error::CannotWaitNode)?;
+        supervisor.last_exit_status = Some(status.to_string());
+
+        if status.success() && restart.policy == RestartPolicy::OnFailure {
+            supervisor_log(format_args!("node exited successfully"));
+            return std::fs::remove_file(&runner_file).map_err(Error::CannotRemoveRunnerFile);
+        }
+
+        if started.elapsed() >= HEALTHY_RUN {
+            backoff = INITIAL_BACKOFF;
+            failures = 0;
         }
+
+        info.supervisor = Some(supervisor.clone());
+        info.save(&runner_file)?;
+
+        if failures >= restart.max_restarts {
+            // the runner file is left behind, flagged as stale by `jorup status`
+            supervisor_log(format_args!(
+                "node exited with {}, giving up after {} restarts in a row",
+                status, failures
+            ));
+            return Err(Error::TooManyRestarts(failures + 1));
+        }
+
+        supervisor_log(format_args!(
+            "node exited with {}, restarting in {}s",
+            status,
+            backoff.as_secs()
+        ));
+        std::thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+        failures += 1;
+        supervisor.restarts += 1;
     }
+}
 
-    pub fn settings(&mut self) -> Result<(), Error> {
-        let info = if let Some(info) = &self.info {
-            info.clone()
-        } else {
-            return Err(Error::NoRunningNode);
-        };
+fn supervisor_log(message: fmt::Arguments) {
+    eprintln!("{} {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+}
 
-        let status = self
-            .jcli()
-            .args(&[
-                "rest",
-                "v0",
-                "settings",
-                "get",
-                "--host",
-                &format!(
-                    "http://localhost:{}/api",
-                    info.rest_port.ok_or(Error::RestNotRunning)?
-                ),
-            ])
-            .status()
-            .map_err(Error::CannotSendStopSignal)?;
-
-        if status.success() {
-            Ok(())
-        } else {
-            Err(Error::CannotStopNode)
+/// Start the node in the background, logging to the log file of the
+/// blockchain directory once the log of the previous node was rotated
+fn start_node(
+    dir: &Path,
+    jormungandr: &Path,
+    arguments: &[String],
+    keep_logs: usize,
+) -> Result<Child, Error> {
+    blockchain::rotate_logs(dir, keep_logs).map_err(Error::CannotRotateLogs)?;
+
+    let log_file = dir.join(blockchain::LOG_FILE);
+    let log = File::create(&log_file).map_err(|e| Error::CannotOpenFile(e, log_file))?;
+
+    Command::new(jormungandr)
+        .args(arguments)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(log)
+        .spawn()
+        .map_err(Error::CannotStartJormungandr)
+}
+
+/// Stop the supervisor of the node, so that it does not restart it
+fn stop_supervisor(supervisor: &SupervisorInfo) -> Result<(), Error> {
+    if !supervisor.is_running()? {
+        return Ok(());
+    }
+
+    println!("**** stopping the supervisor (PID: {})", supervisor.pid);
+    #[cfg(unix)]
+    signal(supervisor.pid, libc::SIGTERM)?;
+    #[cfg(windows)]
+    kill(supervisor.pid)?;
+
+    if !wait_for_exit(|| supervisor.is_running(), KILL_TIMEOUT)? {
+        return Err(Error::CannotStopSupervisor(supervisor.pid));
+    }
+    Ok(())
+}
+
+/// Wait for a process to exit, returns false if it is still running after
+/// `timeout`
+fn wait_for_exit(
+    is_running: impl Fn() -> Result<bool, Error>,
+    timeout: Duration,
+) -> Result<bool, Error> {
+    let start = Instant::now();
+    loop {
+        if !is_running()? {
+            return Ok(true);
+        }
+        if start.elapsed() >= timeout {
+            return Ok(false);
         }
+        std::thread::sleep(Duration::from_millis(200));
     }
+}
 
-    pub fn info(&mut self) -> Result<(), Error> {
-        let info = if let Some(info) = &self.info {
-            info.clone()
-        } else {
-            return Err(Error::NoRunningNode);
-        };
+/// Send SIGTERM to the node, returns false if it is still running after
+/// `timeout`
+#[cfg(unix)]
+fn terminate(info: &RunnerInfo, timeout: Duration) -> Result<bool, Error> {
+    println!("**** sending SIGTERM to the node (PID: {})", info.pid);
+    signal(info.pid, libc::SIGTERM)?;
+    wait_for_exit(|| info.is_node_running(), timeout)
+}
 
-        println!("{:#?}", info);
-
-        let status = self
-            .jcli()
-            .args(&[
-                "rest",
-                "v0",
-                "node",
-                "stats",
-                "get",
-                "--host",
-                &format!(
-                    "http://localhost:{}/api",
-                    info.rest_port.ok_or(Error::RestNotRunning)?
-                ),
-            ])
-            .status()
-            .map_err(Error::CannotSendStopSignal)?;
-
-        if status.success() {
-            Ok(())
-        } else {
-            Err(Error::CannotStopNode)
+#[cfg(unix)]
+fn kill(pid: u32) -> Result<(), Error> {
+    signal(pid, libc::SIGKILL)
+}
+
+#[cfg(unix)]
+fn signal(pid: u32, signal: libc::c_int) -> Result<(), Error> {
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } != 0 {
+        let error = io::Error::last_os_error();
+        // the node exited in the meantime
+        if error.raw_os_error() != Some(libc::ESRCH) {
+            return Err(Error::CannotSignalNode(error));
+        }
+    }
+    Ok(())
+}
+
+/// There is no SIGTERM on Windows, the node can only be killed
+#[cfg(windows)]
+fn terminate(_info: &RunnerInfo, _timeout: Duration) -> Result<bool, Error> {
+    Ok(false)
+}
+
+#[cfg(windows)]
+fn kill(pid: u32) -> Result<(), Error> {
+    use winapi::{
+        shared::minwindef::*,
+        um::{
+            handleapi::CloseHandle,
+            processthreadsapi::{OpenProcess, TerminateProcess},
+            winnt::PROCESS_TERMINATE,
+        },
+    };
+
+    unsafe {
+        let process_handle = OpenProcess(PROCESS_TERMINATE, FALSE, pid as DWORD);
+        if process_handle.is_null() {
+            return Err(Error::CannotSignalNode(io::Error::last_os_error()));
+        }
+        let terminated = TerminateProcess(process_handle, 1);
+        let error = io::Error::last_os_error();
+        CloseHandle(process_handle);
+        if terminated == FALSE {
+            return Err(Error::CannotSignalNode(error));
+        }
+    }
+    Ok(())
+}
+
+impl RunnerInfo {
+    /// Read a runner file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let info = std::fs::read_to_string(path)
+            .map_err(|e| Error::CannotOpenFile(e, path.to_path_buf()))?;
+        serde_json::from_str(&info).map_err(|e| Error::Json(e, path.to_path_buf()))
+    }
+
+    /// The runner file of a node that was just started
+    fn started(child: &Child, rest_port: Option<u16>, jcli: &Path, jormungandr: &Path) -> Self {
+        RunnerInfo {
+            pid: child.id(),
+            rest_port,
+            jcli: jcli.to_path_buf(),
+            jormungandr: jormungandr.to_path_buf(),
+            started_at: Some(Utc::now()),
+            exe: std::fs::canonicalize(jormungandr).ok(),
+            supervisor: None,
+        }
+    }
+
+    /// Write the runner file. It is written next to its destination first so
+    /// that it is never read half written.
+    fn save(&self, path: &Path) -> Result<(), Error> {
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_string(self).unwrap())
+            .and_then(|()| std::fs::rename(&tmp, path))
+            .map_err(|e| Error::CannotWriteFile(e, path.to_path_buf()))
+    }
+
+    /// Whether the node of this runner file is still running, or its
+    /// supervisor which is about to restart it
+    pub fn is_running(&self) -> Result<bool, Error> {
+        if self.is_node_running()? {
+            return Ok(true);
+        }
+        match &self.supervisor {
+            Some(supervisor) => supervisor.is_running(),
+            None => Ok(false),
+        }
+    }
+
+    /// Whether the node itself is still running
+    pub fn is_node_running(&self) -> Result<bool, Error> {
+        check_pid(self.pid, self.exe.as_deref(), self.started_at)
+    }
+
+    /// Stop the node of this runner file and remove the runner file once it
+    /// has exited.
+    ///
+    /// The supervisor of the node, if any, is stopped first so that it does
+    /// not restart the node. The node is asked to shut down through its REST
+    /// API, if enabled. If it is still running after `timeout`, it is sent
+    /// SIGTERM and then, with `force`, killed if still running after `timeout`
+    /// again.
+    pub fn shutdown(
+        &self,
+        runner_file: &Path,
+        timeout: Duration,
+        force: bool,
+    ) -> Result<(), Error> {
+        let mut info = self.clone();
+
+        if let Some(supervisor) = &info.supervisor {
+            stop_supervisor(supervisor)?;
+            // the supervisor may have restarted the node in the meantime
+            if let Ok(latest) = RunnerInfo::load(runner_file) {
+                info = latest;
+            }
         }
+
+        let mut stopped = !info.is_node_running()?;
+        if let (false, Some(rest_port)) = (stopped, info.rest_port) {
+            match RestClient::new(rest_port).and_then(|rest| rest.shutdown()) {
+                Ok(()) => stopped = wait_for_exit(|| info.is_node_running(), timeout)?,
+                Err(err) => eprintln!("WARN: cannot shut down the node through REST: {}", err),
+            }
+        }
+
+        if !stopped {
+            stopped = terminate(&info, timeout)?;
+        }
+
+        if !stopped {
+            if !force {
+                return Err(Error::NodeStillRunning(info.pid));
+            }
+            eprintln!(
+                "WARN: the node is still running, killing it (PID: {})",
+                info.pid
+            );
+            kill(info.pid)?;
+            if !wait_for_exit(|| info.is_node_running(), KILL_TIMEOUT)? {
+                return Err(Error::CannotKillNode(info.pid));
+            }
+        }
+
+        std::fs::remove_file(runner_file).map_err(Error::CannotRemoveRunnerFile)
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn rest_port(&self) -> Option<u16> {
+        self.rest_port
+    }
+
+    pub fn jormungandr(&self) -> &Path {
+        &self.jormungandr
+    }
+
+    /// When the node was started, unknown for nodes started by previous
+    /// versions of jorup
+    pub fn started_at(&self) -> Option<DateTime<Utc>> {
+        self.started_at
+    }
+
+    /// The process restarting the node, if started with `--restart`
+    pub fn supervisor(&self) -> Option<&SupervisorInfo> {
+        self.supervisor.as_ref()
     }
 }
 
+impl SupervisorInfo {
+    pub fn is_running(&self) -> Result<bool, Error> {
+        check_pid(self.pid, self.exe.as_deref(), Some(self.started_at))
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    pub fn restart(&self) -> RestartPolicy {
+        self.restart
+    }
+
+    pub fn max_restarts(&self) -> u32 {
+        self.max_restarts
+    }
+
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    pub fn last_exit_status(&self) -> Option<&str> {
+        self.last_exit_status.as_deref()
+    }
+}
+
+impl FromStr for RestartPolicy {
+    type Err = InvalidRestartPolicy;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "on-failure" => Ok(RestartPolicy::OnFailure),
+            "always" => Ok(RestartPolicy::Always),
+            _ => Err(InvalidRestartPolicy(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RestartPolicy::OnFailure => f.write_str("on-failure"),
+            RestartPolicy::Always => f.write_str("always"),
+        }
+    }
+}
+
+/// Whether a process recorded in a runner file is still running.
+///
+/// Signal 0 checks that a process with the PID exists. As the PID may have
+/// been reused since, the process must also be the recorded binary, started
+/// at the recorded time, and must not have exited.
 #[cfg(unix)]
-fn check_pid(pid: u32) -> Result<bool, Error> {
-    let status = Command::new("ps")
-        .arg(&pid.to_string())
-        .stdout(Stdio::null())
-        .stdin(Stdio::null())
-        .stderr(Stdio::null())
-        .status()
-        .map_err(Error::PidCheck)?;
+fn check_pid(
+    pid: u32,
+    exe: Option<&Path>,
+    started_at: Option<DateTime<Utc>>,
+) -> Result<bool, Error> {
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } != 0 {
+        let error = io::Error::last_os_error();
+        return match error.raw_os_error() {
+            Some(libc::ESRCH) => Ok(false),
+            // the process exists but cannot be signalled, for example because
+            // it runs as another user: whether it is the node is told below
+            Some(libc::EPERM) => Ok(is_same_process(pid, exe, started_at)),
+            _ => Err(Error::PidCheck(error)),
+        };
+    }
 
-    Ok(status.success())
+    Ok(is_same_process(pid, exe, started_at))
+}
+
+/// The clock may have been adjusted since the node started, the start times
+/// are only compared up to this number of seconds.
+#[cfg(target_os = "linux")]
+const START_TIME_TOLERANCE: i64 = 60;
+
+#[cfg(target_os = "linux")]
+fn is_same_process(pid: u32, exe: Option<&Path>, started_at: Option<DateTime<Utc>>) -> bool {
+    let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+
+    if let (Some(exe), Ok(process_exe)) = (exe, std::fs::read_link(proc_dir.join("exe"))) {
+        // the binary may have been replaced while running, for example by
+        // rebuilding a linked release
+        let process_exe = process_exe.to_string_lossy();
+        let process_exe = process_exe.trim_end_matches(" (deleted)");
+        if Path::new(process_exe) != exe {
+            return false;
+        }
+    }
+
+    let stat = match ProcessStat::read(&proc_dir) {
+        Some(stat) => stat,
+        None => return true,
+    };
+
+    // a process that exited but was not reaped by its parent yet
+    if stat.state == 'Z' || stat.state == 'X' {
+        return false;
+    }
+
+    if let Some(started_at) = started_at {
+        if (stat.started_at - started_at).num_seconds().abs() > START_TIME_TOLERANCE {
+            return false;
+        }
+    }
+
+    true
+}
+
+#[cfg(target_os = "linux")]
+struct ProcessStat {
+    state: char,
+    started_at: DateTime<Utc>,
+}
+
+#[cfg(target_os = "linux")]
+impl ProcessStat {
+    /// Read the state of a process and its start time, from the number of
+    /// clock ticks between the boot and the start of the process, in
+    /// `/proc/<pid>/stat`
+    fn read(proc_dir: &Path) -> Option<Self> {
+        use chrono::TimeZone;
+
+        let stat = std::fs::read_to_string(proc_dir.join("stat")).ok()?;
+        // the name of the process comes in parenthesis and may contain
+        // spaces, the state is the first field after it and the start time
+        // the 20th
+        let fields: Vec<_> = stat.rsplit(')').next()?.split_whitespace().collect();
+        let state = fields.first()?.chars().next()?;
+        let start_ticks: i64 = fields.get(19)?.parse().ok()?;
+
+        let boot_time: i64 = std::fs::read_to_string("/proc/stat")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("btime "))?
+            .trim()
+            .parse()
+            .ok()?;
+
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as i64;
+        if ticks_per_second <= 0 {
+            return None;
+        }
+
+        let started_at = Utc.timestamp(boot_time, 0)
+            + chrono::Duration::milliseconds(start_ticks * 1000 / ticks_per_second);
+        Some(ProcessStat { state, started_at })
+    }
+}
+
+/// Without `/proc`, a running process with the PID is assumed to be the node.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_same_process(_pid: u32, _exe: Option<&Path>, _started_at: Option<DateTime<Utc>>) -> bool {
+    true
 }
 
 #[cfg(windows)]
-fn check_pid(pid: u32) -> Result<bool, Error> {
+fn check_pid(
+    pid: u32,
+    _exe: Option<&Path>,
+    _started_at: Option<DateTime<Utc>>,
+) -> Result<bool, Error> {
     use winapi::{
         shared::minwindef::*,
         um::{
@@ -1,5 +1,5 @@
-use chrono::{offset::Utc, DateTime};
-use semver::{Version as SemverVersion, VersionReq as SemverVersionReq};
+use chrono::{offset::Utc, DateTime, NaiveDate};
+use semver::{Identifier, Version as SemverVersion, VersionReq as SemverVersionReq};
 use serde::{de, Deserialize, Deserializer};
 use std::{
     cmp::{Ordering, PartialOrd},
@@ -15,22 +15,77 @@ const DATEFMT: &str = "%Y%m%d";
 pub enum Version {
     Nightly(Option<(SemverVersion, DateTime<Utc>)>),
     Stable(SemverVersion),
+    /// a release registered under a name, for example built from source
+    Custom(String),
 }
 
-#[derive(Debug, Clone)]
-pub enum VersionReq {
+/// A release channel, following the releases of a given stability
+///
+/// Each of the `stable`, `rc`, `beta` and `latest` channels includes the
+/// releases of the channels before it: `rc` follows the stable releases and
+/// the release candidates, `latest` follows every release except nightlies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Stable,
+    Rc,
+    Beta,
     Latest,
     Nightly,
+}
+
+#[derive(Debug, Clone)]
+pub enum VersionReq {
+    Channel(Channel),
     Stable(SemverVersionReq),
     ExactStable(SemverVersion),
+    /// the nightly build of the given day
+    ExactNightly(NaiveDate),
+    /// the custom release of the given name
+    Custom(String),
 }
 
 impl Version {
+    /// Parse a version. A nightly version is either `nightly` or, once
+    /// configured with `configure_nightly`, `<version>-nightly.<YYYYMMDD>`.
     pub fn parse(version: &str) -> Result<Self, SemVerError> {
         if version == "nightly" {
             return Ok(Version::Nightly(None));
         }
-        SemverVersion::parse(version).map(Version::Stable)
+        let mut version = SemverVersion::parse(version)?;
+        let date = match version.pre.as_slice() {
+            [Identifier::AlphaNumeric(nightly), Identifier::Numeric(date)]
+                if nightly == "nightly" =>
+            {
+                NaiveDate::parse_from_str(&date.to_string(), DATEFMT).ok()
+            }
+            _ => None,
+        };
+        match date {
+            Some(date) => {
+                version.pre.clear();
+                Ok(Version::Nightly(Some((version, nightly_datetime(date)))))
+            }
+            None => Ok(Version::Stable(version)),
+        }
+    }
+
+    /// Parse a version or the name of a custom release
+    pub fn parse_name(version: &str) -> Result<Self, SemVerError> {
+        match Version::parse(version) {
+            Err(_) if is_custom_name(version) => Ok(Version::Custom(version.to_string())),
+            result => result,
+        }
+    }
+
+    /// A custom release, if the name is valid. The name must start with a
+    /// letter and can only contain alphanumeric characters, `-`, `_` and `.`.
+    /// It must not be a version, a version requirement or a channel.
+    pub fn custom(name: &str) -> Option<Self> {
+        if is_custom_name(name) {
+            Some(Version::Custom(name.to_string()))
+        } else {
+            None
+        }
     }
 
     pub fn from_git_tag(version: &str) -> Result<Self, SemVerError> {
@@ -42,15 +97,20 @@ impl Version {
         match self {
             Version::Nightly(_) => "nightly".to_string(),
             Version::Stable(version) => format!("v{}", version),
+            Version::Custom(name) => name.clone(),
         }
     }
 
     pub fn configure_nightly(self, last_stable_version: Self, datetime: DateTime<Utc>) -> Self {
         let mut version = match last_stable_version {
             Version::Stable(version) => version,
-            Version::Nightly(_) => panic!("only Stable can be provided to this method"),
+            Version::Nightly(_) | Version::Custom(_) => {
+                panic!("only Stable can be provided to this method")
+            }
         };
         version.increment_patch();
+        // only the day of the nightly build is kept, as in its name
+        let datetime = nightly_datetime(datetime.naive_utc().date());
         match self {
             Version::Nightly(_) => Version::Nightly(Some((version, datetime))),
             v => v,
@@ -58,35 +118,112 @@ impl Version {
     }
 }
 
+fn is_custom_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+        && Version::parse(name).is_err()
+        && VersionReq::parse(name).is_err()
+}
+
+fn nightly_datetime(date: NaiveDate) -> DateTime<Utc> {
+    DateTime::from_utc(date.and_hms(0, 0, 0), Utc)
+}
+
+impl Channel {
+    pub fn parse(channel: &str) -> Option<Self> {
+        match channel {
+            "stable" => Some(Channel::Stable),
+            "rc" => Some(Channel::Rc),
+            "beta" => Some(Channel::Beta),
+            "latest" => Some(Channel::Latest),
+            "nightly" => Some(Channel::Nightly),
+            _ => None,
+        }
+    }
+
+    pub fn matches(self, version: &Version) -> bool {
+        let version = match version {
+            Version::Nightly(_) => return self == Channel::Nightly,
+            Version::Stable(version) => version,
+            Version::Custom(_) => return false,
+        };
+        // the kind of prerelease is given by the first identifier, for
+        // example `rc` for `0.9.0-rc1` or `0.9.0-rc.1`
+        let prerelease = match version.pre.first() {
+            None => return self != Channel::Nightly,
+            Some(identifier) => identifier.to_string(),
+        };
+        let prerelease = prerelease.trim_end_matches(|c: char| c.is_ascii_digit());
+        match self {
+            Channel::Stable | Channel::Nightly => false,
+            Channel::Rc => prerelease == "rc",
+            Channel::Beta => prerelease == "rc" || prerelease == "beta",
+            Channel::Latest => prerelease != "nightly",
+        }
+    }
+}
+
 impl VersionReq {
     pub fn parse(version_req: &str) -> Result<Self, ReqParseError> {
-        if version_req == "nightly" {
-            return Ok(VersionReq::Nightly);
+        if let Some(channel) = Channel::parse(version_req) {
+            return Ok(VersionReq::Channel(channel));
+        }
+        let nightly_date = version_req
+            .strip_prefix("nightly-")
+            .and_then(|date| NaiveDate::parse_from_str(date, DATEFMT).ok());
+        if let Some(date) = nightly_date {
+            return Ok(VersionReq::ExactNightly(date));
         }
         SemverVersionReq::parse(version_req).map(VersionReq::Stable)
     }
 
+    /// Parse a version requirement given on the command line, where a
+    /// version means this exact version rather than a semver requirement.
+    pub fn parse_exact(version_req: &str) -> Result<Self, ReqParseError> {
+        match Version::parse_name(version_req) {
+            Ok(version) => Ok(VersionReq::exact(version)),
+            Err(_) => VersionReq::parse(version_req),
+        }
+    }
+
     pub fn exact(version: Version) -> Self {
         match version {
-            Version::Nightly(_) => VersionReq::Nightly,
+            Version::Nightly(None) => VersionReq::Channel(Channel::Nightly),
+            Version::Nightly(Some((_, datetime))) => {
+                VersionReq::ExactNightly(datetime.naive_utc().date())
+            }
             Version::Stable(version) => VersionReq::ExactStable(version),
+            Version::Custom(name) => VersionReq::Custom(name),
+        }
+    }
+
+    pub fn channel(&self) -> Option<Channel> {
+        match self {
+            VersionReq::Channel(channel) => Some(*channel),
+            _ => None,
         }
     }
 
     pub fn matches(&self, version: &Version) -> bool {
         match self {
-            VersionReq::Latest => false,
-            VersionReq::Nightly => match version {
-                Version::Nightly(_) => true,
-                _ => false,
-            },
+            VersionReq::Channel(channel) => channel.matches(version),
             VersionReq::Stable(version_req) => match version {
-                Version::Nightly(_) => false,
                 Version::Stable(version) => version_req.matches(version),
+                _ => false,
             },
             VersionReq::ExactStable(version_req) => match version {
-                Version::Nightly(_) => false,
                 Version::Stable(other) => version_req.eq(other),
+                _ => false,
+            },
+            VersionReq::ExactNightly(date) => match version {
+                Version::Nightly(Some((_, datetime))) => datetime.naive_utc().date() == *date,
+                _ => false,
+            },
+            VersionReq::Custom(name) => match version {
+                Version::Custom(other) => name == other,
+                _ => false,
             },
         }
     }
@@ -177,6 +314,19 @@ impl fmt::Display for Version {
                 write!(f, "{}-nightly.{}", version, datetime.format(DATEFMT))
             }
             Version::Stable(version) => f.write_str(&version.to_string()),
+            Version::Custom(name) => f.write_str(name),
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Channel::Stable => f.write_str("stable"),
+            Channel::Rc => f.write_str("rc"),
+            Channel::Beta => f.write_str("beta"),
+            Channel::Latest => f.write_str("latest"),
+            Channel::Nightly => f.write_str("nightly"),
         }
     }
 }
@@ -184,10 +334,11 @@ impl fmt::Display for Version {
 impl fmt::Display for VersionReq {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            VersionReq::Latest => f.write_str("latest"),
-            VersionReq::Nightly => f.write_str("nightly"),
+            VersionReq::Channel(channel) => channel.fmt(f),
             VersionReq::Stable(version_req) => f.write_str(&version_req.to_string()),
             VersionReq::ExactStable(version) => f.write_str(&version.to_string()),
+            VersionReq::ExactNightly(date) => write!(f, "nightly-{}", date.format(DATEFMT)),
+            VersionReq::Custom(name) => f.write_str(name),
         }
     }
 }
@@ -197,13 +348,183 @@ impl PartialOrd for Version {
         let res = match self {
             Version::Nightly(datetime) => match other {
                 Version::Nightly(other_datetime) => return datetime.partial_cmp(other_datetime),
-                Version::Stable(_) => Ordering::Less,
+                Version::Stable(_) | Version::Custom(_) => Ordering::Less,
             },
             Version::Stable(version) => match other {
                 Version::Nightly(_) => Ordering::Greater,
                 Version::Stable(other) => version.cmp(other),
+                Version::Custom(_) => Ordering::Less,
+            },
+            Version::Custom(name) => match other {
+                Version::Custom(other) => name.cmp(other),
+                _ => Ordering::Greater,
             },
         };
         Some(res)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> Version {
+        Version::parse_name(version).unwrap()
+    }
+
+    fn date(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, DATEFMT).unwrap()
+    }
+
+    #[test]
+    fn parse_stable() {
+        let parsed = version("0.8.19");
+        assert_eq!(parsed, Version::Stable(SemverVersion::new(0, 8, 19)));
+        assert_eq!(parsed.to_string(), "0.8.19");
+        assert_eq!(Version::from_git_tag("v0.8.19").unwrap(), parsed);
+        assert_eq!(parsed.to_git_tag(), "v0.8.19");
+    }
+
+    #[test]
+    fn parse_nightly() {
+        assert_eq!(version("nightly"), Version::Nightly(None));
+
+        match version("0.9.1-nightly.20261010") {
+            Version::Nightly(Some((version, datetime))) => {
+                assert_eq!(version, SemverVersion::new(0, 9, 1));
+                assert_eq!(datetime.naive_utc().date(), date("20261010"));
+            }
+            other => panic!("not a nightly: {:?}", other),
+        }
+        assert_eq!(
+            version("0.9.1-nightly.20261010").to_string(),
+            "0.9.1-nightly.20261010"
+        );
+    }
+
+    #[test]
+    fn parse_nightly_without_date_is_a_prerelease() {
+        assert!(matches!(version("0.9.1-nightly"), Version::Stable(_)));
+        assert!(matches!(version("0.9.1-nightly.1"), Version::Stable(_)));
+        assert!(matches!(version("0.9.1-nightly.abc"), Version::Stable(_)));
+    }
+
+    #[test]
+    fn configure_nightly() {
+        let published = DateTime::parse_from_rfc3339("2026-10-10T23:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let nightly = Version::Nightly(None).configure_nightly(version("0.9.0"), published);
+        assert_eq!(nightly.to_string(), "0.9.1-nightly.20261010");
+        assert_eq!(version(&nightly.to_string()), nightly);
+    }
+
+    #[test]
+    fn channels() {
+        let stable = version("0.9.0");
+        let rc = version("0.9.0-rc1");
+        let rc_dotted = version("0.9.0-rc.1");
+        let beta = version("0.9.0-beta.2");
+        let alpha = version("0.9.0-alpha1");
+        let nightly = version("0.9.1-nightly.20261010");
+        let custom = version("mydev");
+
+        let matching = |channel: Channel| {
+            [&stable, &rc, &rc_dotted, &beta, &alpha, &nightly, &custom]
+                .iter()
+                .map(|version| channel.matches(version))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            matching(Channel::Stable),
+            [true, false, false, false, false, false, false]
+        );
+        assert_eq!(
+            matching(Channel::Rc),
+            [true, true, true, false, false, false, false]
+        );
+        assert_eq!(
+            matching(Channel::Beta),
+            [true, true, true, true, false, false, false]
+        );
+        assert_eq!(
+            matching(Channel::Latest),
+            [true, true, true, true, true, false, false]
+        );
+        assert_eq!(
+            matching(Channel::Nightly),
+            [false, false, false, false, false, true, false]
+        );
+        assert!(Channel::Nightly.matches(&Version::Nightly(None)));
+    }
+
+    #[test]
+    fn parse_exact() {
+        let req = VersionReq::parse_exact("0.8.19").unwrap();
+        assert!(matches!(req, VersionReq::ExactStable(_)));
+        assert!(req.matches(&version("0.8.19")));
+        assert!(!req.matches(&version("0.8.20")));
+
+        let req = VersionReq::parse_exact("^0.8").unwrap();
+        assert!(matches!(req, VersionReq::Stable(_)));
+        assert!(req.matches(&version("0.8.20")));
+        assert!(!req.matches(&version("0.9.0")));
+
+        for channel in &["stable", "rc", "beta", "latest", "nightly"] {
+            let req = VersionReq::parse_exact(channel).unwrap();
+            assert_eq!(req.channel(), Channel::parse(channel));
+        }
+
+        let req = VersionReq::parse_exact("nightly-20261010").unwrap();
+        assert!(matches!(req, VersionReq::ExactNightly(_)));
+        assert!(req.matches(&version("0.9.1-nightly.20261010")));
+        assert!(!req.matches(&version("0.9.1-nightly.20261011")));
+
+        let req = VersionReq::parse_exact("0.9.1-nightly.20261010").unwrap();
+        assert!(matches!(req, VersionReq::ExactNightly(_)));
+
+        let req = VersionReq::parse_exact("mydev").unwrap();
+        assert!(matches!(req, VersionReq::Custom(_)));
+        assert!(req.matches(&version("mydev")));
+        assert!(!req.matches(&version("otherdev")));
+        assert!(!req.matches(&version("0.8.19")));
+    }
+
+    #[test]
+    fn custom_names() {
+        for name in &["mydev", "dev-1.2", "my_branch", "Feature.X"] {
+            assert_eq!(
+                Version::custom(name),
+                Some(Version::Custom(name.to_string()))
+            );
+        }
+        // versions, channels, requirements and invalid names
+        for name in &[
+            "0.8.19",
+            "1dev",
+            "stable",
+            "latest",
+            "nightly",
+            "nightly-20261010",
+            "x",
+            "my dev",
+            "my/dev",
+            "",
+            "-dev",
+        ] {
+            assert_eq!(Version::custom(name), None, "{}", name);
+        }
+        assert!(Version::parse_name("my/dev").is_err());
+    }
+
+    #[test]
+    fn ordering() {
+        assert!(version("nightly") < version("0.8.19"));
+        assert!(version("0.9.1-nightly.20261010") < version("0.8.19"));
+        assert!(version("0.9.1-nightly.20261010") < version("0.9.1-nightly.20261011"));
+        assert!(version("0.9.0-rc1") < version("0.9.0"));
+        assert!(version("0.9.0") < version("mydev"));
+        assert!(version("adev") < version("bdev"));
+    }
+}